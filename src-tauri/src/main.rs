@@ -7,16 +7,34 @@ use tauri_plugin_dialog::init as dialog_init;
 fn main() {
     tauri::Builder::default()
         .plugin(dialog_init())
+        .manage(vault::OpenVault::default())
+        .manage(vault::PuzzleSolves::default())
+        .setup(|app| {
+            vault::spawn_auto_lock(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             vault::init_vault_tauri,
-            vault::add_file_tauri,
-            vault::add_file_with_custom_name,
-            vault::unlock_vault_tauri,
-            vault::unlock_file_tauri,
-            vault::status_with_password,
+            vault::open_vault_tauri,
+            vault::lock_vault,
+            vault::session_status,
+            vault::session_add_file,
+            vault::session_unlock_file,
+            vault::session_unlock_vault,
+            vault::session_add_file_time_locked,
+            vault::session_add_file_puzzle_locked,
+            vault::session_solve_puzzle,
+            vault::cancel_puzzle_tauri,
+            vault::session_extend_unlock_date,
+            vault::session_rename_entry,
+            vault::session_delete_entry,
+            vault::session_deletion_log,
+            vault::session_set_delete_policy,
+            vault::change_password_tauri,
+            vault::upgrade_argon_params_tauri,
+            vault::rotate_fek_tauri,
             vault::vault_info,
             vault::refresh_server_time,
-            vault::verify_vault_password,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
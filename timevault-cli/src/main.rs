@@ -0,0 +1,393 @@
+//! `timevault` — headless command-line access to TimeVault vaults.
+
+mod password;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use timevault_core::{DeletePolicy, TimePolicy, TimeVerifier, VaultError};
+
+use password::PasswordSource;
+
+#[derive(Parser, Debug)]
+#[command(name = "timevault", version, about = "Manage TimeVault vaults from the command line")]
+struct Cli {
+    /// Vault directory
+    #[arg(long, short = 'd', global = true, env = "TIMEVAULT_DIR", default_value = ".")]
+    vault: PathBuf,
+
+    /// Print machine-readable JSON instead of human-readable text
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(flatten)]
+    password: PasswordSource,
+
+    /// Number of time sources that must agree before the time is trusted
+    #[arg(long, global = true, default_value_t = TimePolicy::default().min_agreeing)]
+    time_quorum: usize,
+
+    /// Largest disagreement, in seconds, between agreeing time sources
+    #[arg(long, global = true, default_value_t = TimePolicy::default().max_skew_secs)]
+    time_max_skew: u64,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a new vault
+    Init,
+    /// Change the vault password without re-encrypting entries
+    ChangePassword,
+    /// Raise the password hashing cost to suit this machine
+    UpgradeKdf {
+        /// Time unlocking should take, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        target_ms: u64,
+    },
+    /// Replace the vault key and re-wrap every entry's key under it
+    RotateKey,
+    /// Encrypt a file into the vault
+    Add {
+        /// File to add
+        file: PathBuf,
+        /// Unlock time as RFC 3339 (e.g. 2030-01-01T00:00:00Z) or Unix seconds
+        #[arg(long, value_parser = parse_unlock_at)]
+        unlock_at: u64,
+        /// Store the entry under this name instead of the file's name
+        #[arg(long)]
+        name: Option<String>,
+        /// Also encrypt the entry to a future drand round, so it cannot be
+        /// decrypted before the unlock time even with the password
+        #[arg(long)]
+        time_lock: bool,
+        /// Wrap the entry in a time-lock puzzle that takes about as long to
+        /// solve on this machine as the time until the unlock date; open it
+        /// offline with `solve` followed by `unlock`
+        #[arg(long, conflicts_with = "time_lock")]
+        puzzle: bool,
+    },
+    /// List the vault's entries
+    List,
+    /// Decrypt one entry whose unlock date has passed
+    Unlock {
+        /// Entry name
+        name: String,
+        /// Output directory
+        #[arg(long, short = 'o')]
+        out: PathBuf,
+    },
+    /// Decrypt every entry whose unlock date has passed
+    UnlockAll {
+        /// Output directory
+        #[arg(long, short = 'o')]
+        out: PathBuf,
+    },
+    /// Work on an entry's time-lock puzzle, resuming from saved progress
+    Solve {
+        /// Entry name
+        name: String,
+        /// Seconds of work between saved checkpoints
+        #[arg(long, default_value_t = 10)]
+        checkpoint_secs: u64,
+    },
+    /// Move an entry's unlock date later
+    Extend {
+        /// Entry name
+        name: String,
+        /// New unlock time, no earlier than the current one
+        #[arg(long, value_parser = parse_unlock_at)]
+        unlock_at: u64,
+    },
+    /// Rename an entry
+    Rename {
+        /// Current entry name
+        name: String,
+        /// New entry name
+        new_name: String,
+    },
+    /// Remove an entry from the vault
+    Delete {
+        /// Entry name
+        name: String,
+    },
+    /// List the entries deleted from the vault
+    Deleted,
+    /// Choose whether entries may be deleted before their unlock date
+    DeletePolicy {
+        /// `any`, or `unlocked-only` to refuse deleting entries that are still locked
+        #[arg(value_parser = parse_delete_policy)]
+        policy: DeletePolicy,
+    },
+    /// Show the vault's creation and last verified time
+    Info,
+    /// Verify the current time against public servers and record it
+    RefreshTime,
+}
+
+fn parse_unlock_at(s: &str) -> Result<u64, String> {
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(secs);
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map_err(|e| format!("expected RFC 3339 time or Unix seconds: {}", e))
+        .and_then(|dt| u64::try_from(dt.timestamp()).map_err(|_| "time is before 1970".to_string()))
+}
+
+fn parse_delete_policy(s: &str) -> Result<DeletePolicy, String> {
+    match s {
+        "any" => Ok(DeletePolicy::Any),
+        "unlocked-only" => Ok(DeletePolicy::UnlockedOnly),
+        _ => Err("expected `any` or `unlocked-only`".to_string()),
+    }
+}
+
+fn path_string(path: &std::path::Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn format_time(secs: u64) -> String {
+    i64::try_from(secs)
+        .ok()
+        .and_then(|s| DateTime::<Utc>::from_timestamp(s, 0))
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_else(|| secs.to_string())
+}
+
+fn time_verifier(cli: &Cli) -> TimeVerifier {
+    let mut verifier = TimeVerifier::default();
+    verifier.policy.min_agreeing = cli.time_quorum;
+    verifier.policy.max_skew_secs = cli.time_max_skew;
+    verifier
+}
+
+async fn run(cli: Cli) -> Result<serde_json::Value> {
+    let vault = path_string(&cli.vault);
+    let verifier = time_verifier(&cli);
+
+    match cli.command {
+        Command::Init => {
+            let password = cli.password.read(true)?;
+            timevault_core::init_vault(vault.clone(), password)?;
+            if !cli.json {
+                println!("Initialized vault in {}", vault);
+            }
+            Ok(json!({ "vault": vault }))
+        }
+        Command::ChangePassword => {
+            let (old_password, new_password) = cli.password.read_change()?;
+            timevault_core::change_password(vault.clone(), old_password, new_password)?;
+            if !cli.json {
+                println!("Changed the password of {}", vault);
+            }
+            Ok(json!({ "vault": vault }))
+        }
+        Command::UpgradeKdf { target_ms } => {
+            let password = cli.password.read(false)?;
+            let calibration = timevault_core::upgrade_argon_params(vault, password, target_ms)?;
+            if !cli.json {
+                println!(
+                    "Argon2id now uses {} MiB, {} iterations, {} lane(s) ({} ms measured, {} ms target)",
+                    calibration.mem_kib / 1024,
+                    calibration.iters,
+                    calibration.parallelism,
+                    calibration.measured_ms,
+                    calibration.target_ms
+                );
+            }
+            Ok(serde_json::to_value(calibration)?)
+        }
+        Command::RotateKey => {
+            let password = cli.password.read(false)?;
+            let moved = timevault_core::rotate_fek(vault, password)?;
+            if !cli.json {
+                println!("Rotated the vault key for {} entries", moved);
+            }
+            Ok(json!({ "entries": moved }))
+        }
+        Command::Add { file, unlock_at, name, time_lock, puzzle } => {
+            let password = cli.password.read(false)?;
+            if puzzle {
+                timevault_core::add_file_puzzle_locked(vault, path_string(&file), password, unlock_at, name)?;
+            } else if time_lock {
+                timevault_core::add_file_time_locked(vault, path_string(&file), password, unlock_at, name)?;
+            } else {
+                timevault_core::add_file_with_name(vault, path_string(&file), password, unlock_at, name)?;
+            }
+            if !cli.json {
+                println!("Added {} (locked until {})", file.display(), format_time(unlock_at));
+            }
+            Ok(json!({ "file": path_string(&file), "unlock_at": unlock_at, "time_lock": time_lock, "puzzle": puzzle }))
+        }
+        Command::List => {
+            let password = cli.password.read(false)?;
+            let entries = timevault_core::get_status_with_password(vault, password)?;
+            if !cli.json {
+                for entry in &entries {
+                    if let Some(warnings) = entry["_tampering_warnings"].as_array() {
+                        for warning in warnings {
+                            eprintln!("{}", warning.as_str().unwrap_or_default());
+                        }
+                    } else {
+                        let unlock_at = entry["file_unlock_date"].as_u64().unwrap_or_default();
+                        let mut lock = String::new();
+                        if let Some(round) = entry["time_lock_round"].as_u64() {
+                            lock = format!("\t(drand round {})", round);
+                        } else if let Some(total) = entry["puzzle"]["squarings"].as_u64() {
+                            let done = entry["puzzle"]["done"].as_u64().unwrap_or_default();
+                            lock = format!("\t(puzzle {:.1}% solved)", done as f64 * 100.0 / total.max(1) as f64);
+                        }
+                        println!("{}\t{}{}", format_time(unlock_at), entry["filename"].as_str().unwrap_or_default(), lock);
+                    }
+                }
+            }
+            Ok(json!({ "entries": entries }))
+        }
+        Command::Unlock { name, out } => {
+            let password = cli.password.read(false)?;
+            let message = timevault_core::unlock_file_with_verifier(vault, path_string(&out), password, name, &verifier).await?;
+            if !cli.json {
+                println!("{}", message);
+            }
+            Ok(json!({ "message": message }))
+        }
+        Command::UnlockAll { out } => {
+            let password = cli.password.read(false)?;
+            let message = timevault_core::unlock_vault_with_verifier(vault, path_string(&out), password, &verifier).await?;
+            if !cli.json {
+                println!("{}", message);
+            }
+            Ok(json!({ "message": message }))
+        }
+        Command::Solve { name, checkpoint_secs } => {
+            let password = cli.password.read(false)?;
+            let json_output = cli.json;
+            timevault_core::solve_puzzle(vault, password, name.clone(), checkpoint_secs, |done, total| {
+                if !json_output {
+                    eprintln!("{}: {:.1}% ({} of {} squarings)", name, done as f64 * 100.0 / total as f64, done, total);
+                }
+                true
+            })?;
+            if !cli.json {
+                println!("Solved the time-lock puzzle for {}; unlock it with `timevault unlock {}`", name, name);
+            }
+            Ok(json!({ "name": name, "solved": true }))
+        }
+        Command::Extend { name, unlock_at } => {
+            let password = cli.password.read(false)?;
+            timevault_core::extend_unlock_date(vault, password, name.clone(), unlock_at)?;
+            if !cli.json {
+                println!("{} is now locked until {}", name, format_time(unlock_at));
+            }
+            Ok(json!({ "name": name, "unlock_at": unlock_at }))
+        }
+        Command::Rename { name, new_name } => {
+            let password = cli.password.read(false)?;
+            timevault_core::rename_entry(vault, password, name.clone(), new_name.clone())?;
+            if !cli.json {
+                println!("Renamed {} to {}", name, new_name);
+            }
+            Ok(json!({ "name": name, "new_name": new_name }))
+        }
+        Command::Delete { name } => {
+            let password = cli.password.read(false)?;
+            timevault_core::delete_entry_with_verifier(vault, password, name.clone(), &verifier).await?;
+            if !cli.json {
+                println!("Deleted {}", name);
+            }
+            Ok(json!({ "name": name }))
+        }
+        Command::Deleted => {
+            let password = cli.password.read(false)?;
+            let records = timevault_core::deletion_log(vault, password)?;
+            if !cli.json {
+                for record in &records {
+                    println!("{}\t{}\t(was locked until {})", format_time(record.deleted_at), record.filename, format_time(record.file_unlock_date));
+                }
+            }
+            Ok(json!({ "deleted": records }))
+        }
+        Command::DeletePolicy { policy } => {
+            let password = cli.password.read(false)?;
+            timevault_core::set_delete_policy_with_verifier(vault, password, policy, &verifier).await?;
+            if !cli.json {
+                match policy {
+                    DeletePolicy::Any => println!("Any entry may now be deleted"),
+                    DeletePolicy::UnlockedOnly => println!("Entries can now only be deleted after their unlock date"),
+                }
+            }
+            Ok(json!({ "policy": policy }))
+        }
+        Command::Info => {
+            let info = timevault_core::vault_info(vault.clone())?
+                .ok_or_else(|| anyhow!("No vault found in {}", vault))?;
+            if !cli.json {
+                println!("Created:            {}", format_time(info.created));
+                if info.last_server_time == 0 {
+                    println!("Last verified time: never");
+                } else {
+                    println!("Last verified time: {}", format_time(info.last_server_time));
+                }
+            }
+            Ok(serde_json::to_value(info)?)
+        }
+        Command::RefreshTime => {
+            let info = timevault_core::refresh_server_time_with_verifier(vault, &verifier).await?;
+            if !cli.json {
+                println!(
+                    "Verified time {} from {}",
+                    format_time(info.last_server_time),
+                    info.time_source.as_deref().unwrap_or("server")
+                );
+            }
+            Ok(serde_json::to_value(info)?)
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let json_output = cli.json;
+
+    match run(cli).await {
+        Ok(value) => {
+            if json_output {
+                println!("{}", json!({ "ok": true, "result": value }));
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            if json_output {
+                println!("{}", json!({ "ok": false, "error": VaultError::from(e) }));
+            } else {
+                eprintln!("error: {}", e);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_is_well_formed() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_unlock_times() {
+        assert_eq!(parse_unlock_at("1893456000"), Ok(1_893_456_000));
+        assert_eq!(parse_unlock_at("2030-01-01T00:00:00Z"), Ok(1_893_456_000));
+        assert!(parse_unlock_at("1969-12-31T23:59:59Z").is_err());
+        assert!(parse_unlock_at("tomorrow").is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use clap::Args;
+use std::io::{self, BufRead};
+
+/// Where the vault password is read from. The password is never accepted on
+/// the command line so it does not leak into shell history or `ps` output.
+#[derive(Args, Debug)]
+pub struct PasswordSource {
+    /// Read the password from the first line of standard input
+    #[arg(long, global = true, conflicts_with = "password_fd")]
+    pub password_stdin: bool,
+
+    /// Read the password from an already-open file descriptor (Unix only)
+    #[arg(long, global = true, value_name = "FD")]
+    pub password_fd: Option<i32>,
+}
+
+impl PasswordSource {
+    /// Reads the password, prompting on the terminal if no other source was
+    /// given. With `confirm`, the terminal prompt asks for it twice.
+    pub fn read(&self, confirm: bool) -> Result<String> {
+        let password = if self.password_stdin {
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line)?;
+            strip_newline(line)
+        } else if let Some(fd) = self.password_fd {
+            strip_newline(read_fd(fd)?)
+        } else {
+            let password = rpassword::prompt_password("Vault password: ")?;
+            if confirm {
+                let again = rpassword::prompt_password("Confirm password: ")?;
+                if again != password {
+                    return Err(anyhow!("Passwords do not match"));
+                }
+            }
+            password
+        };
+
+        if password.is_empty() {
+            return Err(anyhow!("Password must not be empty"));
+        }
+        Ok(password)
+    }
+}
+
+impl PasswordSource {
+    /// Reads the current and a new password for a password change. From
+    /// standard input or a file descriptor they are the first two lines; on
+    /// the terminal the new password is asked for twice.
+    pub fn read_change(&self) -> Result<(String, String)> {
+        let (old, new) = if self.password_stdin {
+            let mut stdin = io::stdin().lock();
+            let mut old = String::new();
+            let mut new = String::new();
+            stdin.read_line(&mut old)?;
+            stdin.read_line(&mut new)?;
+            (strip_newline(old), strip_newline(new))
+        } else if let Some(fd) = self.password_fd {
+            let content = read_fd_to_string(fd)?;
+            let mut lines = content.lines();
+            let old = lines.next().unwrap_or_default().to_string();
+            let new = lines.next().unwrap_or_default().to_string();
+            (old, new)
+        } else {
+            let old = rpassword::prompt_password("Current password: ")?;
+            let new = rpassword::prompt_password("New password: ")?;
+            let again = rpassword::prompt_password("Confirm new password: ")?;
+            if again != new {
+                return Err(anyhow!("Passwords do not match"));
+            }
+            (old, new)
+        };
+
+        if old.is_empty() || new.is_empty() {
+            return Err(anyhow!("Password must not be empty"));
+        }
+        Ok((old, new))
+    }
+}
+
+fn strip_newline(mut s: String) -> String {
+    while s.ends_with('\n') || s.ends_with('\r') {
+        s.pop();
+    }
+    s
+}
+
+fn read_fd(fd: i32) -> Result<String> {
+    Ok(read_fd_to_string(fd)?.lines().next().unwrap_or_default().to_string())
+}
+
+#[cfg(unix)]
+fn read_fd_to_string(fd: i32) -> Result<String> {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::unix::io::FromRawFd;
+
+    // SAFETY: the caller hands us ownership of `fd` for the lifetime of the
+    // process; it is read once and closed when `file` is dropped.
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    let mut buf = String::new();
+    file.read_to_string(&mut buf)?;
+    Ok(buf)
+}
+
+#[cfg(not(unix))]
+fn read_fd_to_string(_fd: i32) -> Result<String> {
+    Err(anyhow!("--password-fd is only supported on Unix"))
+}
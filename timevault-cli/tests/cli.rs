@@ -0,0 +1,70 @@
+//! Smoke tests that run the `timevault` binary against a scratch vault.
+
+use serde_json::Value;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const PASSWORD: &str = "correct horse battery staple";
+/// 2100-01-01T00:00:00Z, far enough ahead to stay locked.
+const UNLOCK_AT: &str = "4102444800";
+
+/// Runs `timevault --json` on `vault` with `args`, writing `stdin` to its
+/// standard input, and returns whether it succeeded and its JSON output.
+fn timevault(vault: &Path, args: &[&str], stdin: &str) -> (bool, Value) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_timevault"))
+        .arg("--vault")
+        .arg(vault)
+        .arg("--json")
+        .args(args)
+        .env_remove("TIMEVAULT_DIR")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value["ok"].as_bool(), Some(output.status.success()), "{}", value);
+    (output.status.success(), value)
+}
+
+#[test]
+fn adds_and_lists_with_the_password_on_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+    let vault = dir.path().join("vault");
+    let file = dir.path().join("secret.txt");
+    std::fs::write(&file, b"later").unwrap();
+    let password = format!("{}\n", PASSWORD);
+
+    let (ok, out) = timevault(&vault, &["init", "--password-stdin"], &password);
+    assert!(ok, "{}", out);
+
+    let file = file.to_string_lossy();
+    let (ok, out) = timevault(&vault, &["add", &file, "--unlock-at", UNLOCK_AT, "--password-stdin"], &password);
+    assert!(ok, "{}", out);
+    assert_eq!(out["result"]["unlock_at"], 4_102_444_800u64);
+
+    let (ok, out) = timevault(&vault, &["list", "--password-stdin"], &password);
+    assert!(ok, "{}", out);
+    let entries = out["result"]["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["filename"], "secret.txt");
+    assert_eq!(entries[0]["file_unlock_date"], 4_102_444_800u64);
+}
+
+#[test]
+fn reports_errors_as_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let vault = dir.path().join("vault");
+    let (ok, _) = timevault(&vault, &["init", "--password-stdin"], &format!("{}\n", PASSWORD));
+    assert!(ok);
+
+    let (ok, out) = timevault(&vault, &["list", "--password-stdin"], "not the password\n");
+    assert!(!ok);
+    assert_eq!(out["error"]["kind"], "wrong_password", "{}", out);
+
+    let (ok, out) = timevault(&vault, &["list", "--password-stdin"], "\n");
+    assert!(!ok);
+    assert!(out["error"]["message"].as_str().unwrap().contains("must not be empty"), "{}", out);
+}
@@ -0,0 +1,28 @@
+//! Feeds arbitrary records to `decrypt_file_metadata`, both as a
+//! `files_meta/*.meta.json` file and as raw ciphertext and nonce bytes. No
+//! record the fuzzer makes up can authenticate, so every one must be
+//! rejected.
+
+#![no_main]
+
+use base64::{engine::general_purpose, Engine as _};
+use libfuzzer_sys::fuzz_target;
+use timevault_core::crypto::decrypt_file_metadata;
+use timevault_core::EncryptedFileMeta;
+
+const FEK: [u8; 32] = [0x5a; 32];
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(record) = serde_json::from_slice::<EncryptedFileMeta>(data) {
+        assert!(decrypt_file_metadata(&FEK, &record.encrypted_payload_b64, &record.metadata_nonce_b64).is_err());
+    }
+
+    // The first byte picks the nonce length, so wrong lengths are covered
+    // as often as the correct one.
+    if let Some((&nonce_len, rest)) = data.split_first() {
+        let (nonce, encrypted) = rest.split_at((nonce_len as usize % 32).min(rest.len()));
+        let encrypted_b64 = general_purpose::STANDARD.encode(encrypted);
+        let nonce_b64 = general_purpose::STANDARD.encode(nonce);
+        assert!(decrypt_file_metadata(&FEK, &encrypted_b64, &nonce_b64).is_err());
+    }
+});
@@ -0,0 +1,19 @@
+//! Feeds arbitrary request and reply packets, and arbitrary local clock
+//! readings, to `parse_reply`. Any input may be rejected, but none may
+//! panic or overflow.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use timevault_core::time::ntp::{parse_reply, PACKET_LEN};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 2 * PACKET_LEN + 16 {
+        return;
+    }
+    let (request, rest) = data.split_at(PACKET_LEN);
+    let (reply, clock) = rest.split_at(PACKET_LEN);
+    let t1 = i64::from_le_bytes(clock[..8].try_into().unwrap());
+    let t4 = i64::from_le_bytes(clock[8..16].try_into().unwrap());
+    let _ = parse_reply(request.try_into().unwrap(), reply.try_into().unwrap(), t1 as i128, t4 as i128);
+});
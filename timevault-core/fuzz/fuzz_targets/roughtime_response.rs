@@ -0,0 +1,18 @@
+//! Feeds arbitrary datagrams to the Roughtime message parser and to
+//! `verify_response`. Nothing the fuzzer makes up is signed by the server's
+//! key, so every response must be rejected.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use timevault_core::time::roughtime::{verify_response, Message, NONCE_LEN};
+
+const PUBLIC_KEY: [u8; 32] = [0x5a; 32];
+const NONCE: [u8; NONCE_LEN] = [0x42; NONCE_LEN];
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(message) = Message::parse(data) {
+        let _ = message.get(u32::from_le_bytes(*b"SREP"));
+    }
+    assert!(verify_response(&PUBLIC_KEY, &NONCE, data).is_err());
+});
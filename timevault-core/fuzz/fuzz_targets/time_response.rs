@@ -0,0 +1,16 @@
+//! Parses arbitrary bytes as a response from each public JSON time API, as
+//! `fetch_public_unixtime_with_retries` does with every server's reply.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use timevault_core::time::{JsonTimeApi, JsonTimeFormat};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = serde_json::from_slice::<serde_json::Value>(data) else {
+        return;
+    };
+    for format in [JsonTimeFormat::UnixTime, JsonTimeFormat::DateTime, JsonTimeFormat::WorldClock] {
+        let _ = JsonTimeApi::new("fuzz", "https://time.example", format).parse(&json);
+    }
+});
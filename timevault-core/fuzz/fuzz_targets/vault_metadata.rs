@@ -0,0 +1,15 @@
+//! Parses arbitrary bytes as `vault_metadata.json`. Anything accepted must
+//! survive a write and re-read unchanged.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use timevault_core::metadata::parse_vault_metadata;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(meta) = parse_vault_metadata(data) {
+        let written = serde_json::to_vec(&meta).unwrap();
+        let reread = parse_vault_metadata(&written).expect("a parsed header must parse again");
+        assert_eq!(serde_json::to_value(&reread).unwrap(), serde_json::to_value(&meta).unwrap());
+    }
+});
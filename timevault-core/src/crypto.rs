@@ -0,0 +1,311 @@
+//! Key derivation and authenticated encryption primitives.
+
+use anyhow::{anyhow, Result};
+use argon2::{Argon2, Params};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+use crate::error::VaultError;
+use crate::metadata::{ArgonCalibration, DeletePolicy, FileMetaPayload, VaultMetadata, WrappedKey};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Argon2id parameters used for new vaults, as `(memory KiB, iterations, lanes)`.
+pub fn default_argon_params() -> (u32, u32, u32) {
+    (128 * 1024, 4, 1)
+}
+
+/// Largest Argon2 memory cost [`calibrate_argon_params`] will pick: 1 GiB.
+/// Headers asking for more are rejected as corrupt rather than attempting
+/// the allocation.
+pub(crate) const MAX_CALIBRATED_MEM_KIB: u32 = 1024 * 1024;
+/// Largest Argon2 iteration count [`calibrate_argon_params`] will pick.
+pub(crate) const MAX_ARGON_ITERS: u32 = 64;
+/// Largest number of Argon2 lanes a vault may use.
+pub(crate) const MAX_ARGON_PARALLELISM: u32 = 16;
+
+/// Checks Argon2 parameters read from a vault header against the bounds
+/// TimeVault itself would ever choose, so that a doctored header cannot make
+/// key derivation run for hours or attempt an enormous allocation.
+pub(crate) fn check_argon_params(mem_kib: u32, iters: u32, parallelism: u32) -> Result<(), VaultError> {
+    let corrupt = |reason: String| Err(VaultError::CorruptMetadata { reason });
+    if !(1..=MAX_ARGON_PARALLELISM).contains(&parallelism) {
+        return corrupt(format!("Argon2 lane count must be between 1 and {}", MAX_ARGON_PARALLELISM));
+    }
+    if !(1..=MAX_ARGON_ITERS).contains(&iters) {
+        return corrupt(format!("Argon2 iteration count must be between 1 and {}", MAX_ARGON_ITERS));
+    }
+    if mem_kib > MAX_CALIBRATED_MEM_KIB {
+        return corrupt("Argon2 memory cost is above the 1 GiB limit".to_string());
+    }
+    if mem_kib < 8 * parallelism {
+        return corrupt("Argon2 memory cost is below 8 KiB per lane".to_string());
+    }
+    Ok(())
+}
+
+/// Benchmarks [`derive_key`] on this machine and picks Argon2id parameters
+/// that take about `target_ms` to derive a key, never weaker than `minimum`
+/// (as `(memory KiB, iterations, lanes)`, e.g. [`default_argon_params`]).
+///
+/// Memory is raised first, doubling while the minimum number of iterations
+/// would still fit in the target, since memory cost is what makes GPU and
+/// ASIC attacks expensive; iterations then fill the remaining time, up to
+/// [`MAX_ARGON_ITERS`].
+pub fn calibrate_argon_params(target_ms: u64, minimum: (u32, u32, u32)) -> Result<ArgonCalibration> {
+    let (mut mem_kib, min_iters, parallelism) = minimum;
+    let min_iters = min_iters.max(1);
+    let time_derivation = |mem_kib: u32, iters: u32| -> Result<u64> {
+        let started = Instant::now();
+        derive_key("timevault calibration", &[0u8; 16], mem_kib, iters, parallelism)?;
+        Ok(started.elapsed().as_millis().max(1) as u64)
+    };
+
+    let mut pass_ms = time_derivation(mem_kib, 1)?;
+    while mem_kib.saturating_mul(2) <= MAX_CALIBRATED_MEM_KIB
+        && pass_ms.saturating_mul(2).saturating_mul(min_iters as u64) <= target_ms
+    {
+        mem_kib *= 2;
+        pass_ms = time_derivation(mem_kib, 1)?;
+    }
+
+    let iters = u32::try_from(target_ms / pass_ms).unwrap_or(u32::MAX).clamp(min_iters.min(MAX_ARGON_ITERS), MAX_ARGON_ITERS);
+    let measured_ms = time_derivation(mem_kib, iters)?;
+
+    Ok(ArgonCalibration {
+        mem_kib,
+        iters,
+        parallelism,
+        target_ms,
+        measured_ms,
+        calibrated_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    })
+}
+
+/// Derives a 32-byte key from `password` and `salt` with Argon2id.
+pub fn derive_key(password: &str, salt: &[u8], mem_kib: u32, iters: u32, parallelism: u32) -> Result<[u8; 32]> {
+    let params = Params::new(mem_kib, iters, parallelism, None).map_err(|e| anyhow!(e.to_string()))?;
+    let argon = Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params);
+
+    let mut out = [0u8; 32];
+    argon.hash_password_into(password.as_bytes(), salt, &mut out).map_err(|e| anyhow!(e.to_string()))?;
+    Ok(out)
+}
+
+/// Associated data binding the wrapped FEK to vault versions that carry a
+/// state MAC, so the header cannot be downgraded to skip MAC verification.
+const FEK_WRAP_AAD: &[u8] = b"timevault fek wrap v3";
+
+/// Label for deriving the vault state MAC key from the FEK.
+const STATE_MAC_LABEL: &[u8] = b"timevault vault state mac v1";
+const DATA_KEY_WRAP_AAD: &[u8] = b"timevault data key wrap v1";
+
+/// Derives the wrapping key from `password` and unwraps the vault FEK.
+///
+/// The Argon2 parameters are checked against [`check_argon_params`] first.
+/// Within those bounds, an edited parameter only changes the derived key, so
+/// the FEK fails to unwrap and the edit is reported as
+/// [`VaultError::WrongPassword`] rather than `Tampered`; the state MAC can
+/// only be checked once the FEK is known.
+pub(crate) fn unwrap_fek(meta: &VaultMetadata, password: &str) -> Result<[u8; 32]> {
+    check_argon_params(meta.argon_mem_kib, meta.argon_iters, meta.argon_parallelism)?;
+    let salt = general_purpose::STANDARD.decode(&meta.salt_b64)?;
+    let mut derived = derive_key(password, &salt, meta.argon_mem_kib, meta.argon_iters, meta.argon_parallelism)?;
+    let result = unwrap_fek_with_key(meta, &derived);
+    derived.zeroize();
+    result
+}
+
+fn unwrap_fek_with_key(meta: &VaultMetadata, derived: &[u8; 32]) -> Result<[u8; 32]> {
+    let wrapped = general_purpose::STANDARD.decode(&meta.wrapped_fek_b64)?;
+    let wrap_nonce = general_purpose::STANDARD.decode(&meta.wrap_nonce_b64)?;
+    if wrap_nonce.len() != 24 {
+        return Err(VaultError::CorruptMetadata {
+            reason: "bad wrap nonce length".to_string(),
+        }
+        .into());
+    }
+
+    let aead = XChaCha20Poly1305::new(Key::from_slice(derived));
+    let nonce = XNonce::from_slice(&wrap_nonce);
+    let aad = if meta.version >= 3 { FEK_WRAP_AAD } else { b"" };
+    let mut fek = match aead.decrypt(nonce, Payload { msg: &wrapped, aad }) {
+        Ok(fek) => fek,
+        Err(_) => {
+            if meta.version < 3 && aead.decrypt(nonce, Payload { msg: &wrapped, aad: FEK_WRAP_AAD }).is_ok() {
+                return Err(VaultError::tampered("Vault metadata integrity check failed").into());
+            }
+            return Err(VaultError::WrongPassword.into());
+        }
+    };
+
+    let mut fek_arr = [0u8; 32];
+    if fek.len() != 32 {
+        fek.zeroize();
+        return Err(anyhow!("FEK length is invalid"));
+    }
+    fek_arr.copy_from_slice(&fek[0..32]);
+    fek.zeroize();
+    Ok(fek_arr)
+}
+
+/// Wraps `fek` under the password-derived key `derived`, returning the
+/// base64 ciphertext and nonce for [`VaultMetadata`].
+pub(crate) fn wrap_fek(derived: &[u8; 32], fek: &[u8; 32]) -> Result<(String, String)> {
+    let aead = XChaCha20Poly1305::new(Key::from_slice(derived));
+    let mut wrap_nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut wrap_nonce);
+    let wrapped = aead.encrypt(XNonce::from_slice(&wrap_nonce), Payload { msg: fek, aad: FEK_WRAP_AAD })?;
+
+    Ok((
+        general_purpose::STANDARD.encode(&wrapped),
+        general_purpose::STANDARD.encode(wrap_nonce),
+    ))
+}
+
+/// Header fields covered by the state MAC. Everything except the MAC itself
+/// and the unauthenticated `last_verified_time` cache.
+#[derive(Serialize)]
+struct AuthenticatedState<'a> {
+    version: u8,
+    salt_b64: &'a str,
+    argon_mem_kib: u32,
+    argon_iters: u32,
+    argon_parallelism: u32,
+    wrapped_fek_b64: &'a str,
+    wrap_nonce_b64: &'a str,
+    creation_ts: u64,
+    authenticated_time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    argon_calibration: Option<&'a ArgonCalibration>,
+    #[serde(skip_serializing_if = "DeletePolicy::is_any")]
+    delete_policy: DeletePolicy,
+}
+
+fn state_mac(fek: &[u8; 32], meta: &VaultMetadata) -> Result<HmacSha256> {
+    let mut kdf = <HmacSha256 as Mac>::new_from_slice(fek).map_err(|e| anyhow!(e.to_string()))?;
+    kdf.update(STATE_MAC_LABEL);
+    let mut mac_key = kdf.finalize().into_bytes();
+
+    let state = AuthenticatedState {
+        version: meta.version,
+        salt_b64: &meta.salt_b64,
+        argon_mem_kib: meta.argon_mem_kib,
+        argon_iters: meta.argon_iters,
+        argon_parallelism: meta.argon_parallelism,
+        wrapped_fek_b64: &meta.wrapped_fek_b64,
+        wrap_nonce_b64: &meta.wrap_nonce_b64,
+        creation_ts: meta.creation_ts,
+        authenticated_time: meta.authenticated_time,
+        argon_calibration: meta.argon_calibration.as_ref(),
+        delete_policy: meta.delete_policy,
+    };
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(&mac_key).map_err(|e| anyhow!(e.to_string()))?;
+    mac_key.zeroize();
+    mac.update(&serde_json::to_vec(&state)?);
+    Ok(mac)
+}
+
+/// Recomputes `meta.state_mac_b64` after the header has been changed.
+pub(crate) fn seal_vault_state(fek: &[u8; 32], meta: &mut VaultMetadata) -> Result<()> {
+    let tag = state_mac(fek, meta)?.finalize().into_bytes();
+    meta.state_mac_b64 = general_purpose::STANDARD.encode(tag);
+    Ok(())
+}
+
+/// Checks `meta.state_mac_b64`, detecting edits to the anti-rollback
+/// watermark, delete policy or version. Edits to the Argon2 parameters or
+/// salt already stop [`unwrap_fek`] from recovering the FEK, and surface as
+/// a wrong password before this check is reached.
+pub(crate) fn verify_vault_state(fek: &[u8; 32], meta: &VaultMetadata) -> Result<()> {
+    let tag = general_purpose::STANDARD.decode(&meta.state_mac_b64).unwrap_or_default();
+    state_mac(fek, meta)?
+        .verify_slice(&tag)
+        .map_err(|_| VaultError::tampered("Vault metadata integrity check failed").into())
+}
+
+/// Encrypts a metadata record (an entry's [`FileMetaPayload`] or another
+/// record kept alongside entries) under the FEK, returning the base64
+/// ciphertext and nonce for an [`EncryptedFileMeta`](crate::EncryptedFileMeta).
+pub fn encrypt_file_metadata<T: Serialize>(fek: &[u8; 32], payload: &T) -> Result<(String, String)> {
+    let aead = XChaCha20Poly1305::new(Key::from_slice(fek));
+    let mut nonce_bytes = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    
+    let payload_json = serde_json::to_vec(payload)?;
+    let encrypted = aead.encrypt(XNonce::from_slice(&nonce_bytes), payload_json.as_ref())?;
+    
+    Ok((
+        general_purpose::STANDARD.encode(&encrypted),
+        general_purpose::STANDARD.encode(nonce_bytes)
+    ))
+}
+
+/// Decrypts and parses an entry's [`FileMetaPayload`]. Fails with
+/// [`VaultError::Tampered`] if the record does not authenticate under `fek`.
+pub fn decrypt_file_metadata(fek: &[u8; 32], encrypted_b64: &str, nonce_b64: &str) -> Result<FileMetaPayload> {
+    let decrypted = decrypt_file_metadata_bytes(fek, encrypted_b64, nonce_b64)?;
+    let payload: FileMetaPayload = serde_json::from_slice(&decrypted)?;
+    Ok(payload)
+}
+
+/// Decrypts a metadata record without interpreting it, for callers that need
+/// to parse older payload formats.
+pub(crate) fn decrypt_file_metadata_bytes(fek: &[u8; 32], encrypted_b64: &str, nonce_b64: &str) -> Result<Vec<u8>> {
+    let aead = XChaCha20Poly1305::new(Key::from_slice(fek));
+    let encrypted = general_purpose::STANDARD.decode(encrypted_b64)?;
+    let nonce = general_purpose::STANDARD.decode(nonce_b64)?;
+    if nonce.len() != 24 {
+        return Err(anyhow!("Invalid nonce length"));
+    }
+
+    aead.decrypt(XNonce::from_slice(&nonce), encrypted.as_ref())
+        .map_err(|_| VaultError::tampered("Metadata decryption failed").into())
+}
+
+fn data_key_aad(blob_id: &str) -> Vec<u8> {
+    [DATA_KEY_WRAP_AAD, blob_id.as_bytes()].concat()
+}
+
+/// Wraps an entry's data key under the FEK for the entry stored as `blob_id`.
+pub(crate) fn wrap_data_key(fek: &[u8; 32], blob_id: &str, key: &[u8; 32]) -> Result<WrappedKey> {
+    let aead = XChaCha20Poly1305::new(Key::from_slice(fek));
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+    let wrapped = aead
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: key, aad: &data_key_aad(blob_id) })
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    Ok(WrappedKey {
+        nonce_b64: general_purpose::STANDARD.encode(nonce),
+        wrapped_key_b64: general_purpose::STANDARD.encode(wrapped),
+    })
+}
+
+/// Recovers the data key of the entry stored as `blob_id`.
+pub(crate) fn unwrap_data_key(fek: &[u8; 32], blob_id: &str, wrapped: &WrappedKey) -> Result<[u8; 32]> {
+    let nonce = general_purpose::STANDARD.decode(&wrapped.nonce_b64)?;
+    let sealed = general_purpose::STANDARD.decode(&wrapped.wrapped_key_b64)?;
+    if nonce.len() != 24 {
+        return Err(anyhow!("Invalid nonce length"));
+    }
+    let mut plain = XChaCha20Poly1305::new(Key::from_slice(fek))
+        .decrypt(XNonce::from_slice(&nonce), Payload { msg: &sealed, aad: &data_key_aad(blob_id) })
+        .map_err(|_| VaultError::tampered("Data key decryption failed"))?;
+    let key = <[u8; 32]>::try_from(plain.as_slice()).map_err(|_| anyhow!("Invalid data key length"));
+    plain.zeroize();
+    key
+}
+
+/// Hex-encoded SHA-256 of `data`.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
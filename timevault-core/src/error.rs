@@ -0,0 +1,205 @@
+//! Errors callers may need to tell apart.
+//!
+//! The vault functions return [`anyhow::Result`], and the failures a caller
+//! can act on are raised as a [`VaultError`] inside it. Convert with
+//! `VaultError::from` (or `downcast_ref::<VaultError>()`) instead of matching
+//! on message text; anything else becomes [`VaultError::Io`],
+//! [`VaultError::CorruptMetadata`] or [`VaultError::Other`].
+//!
+//! A `VaultError` serializes to a JSON object with a snake_case `kind`, the
+//! display `message` and the variant's fields, e.g.
+//! `{"kind": "file_exists", "message": "...", "name": "notes.txt"}`.
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::io;
+
+#[derive(Debug, thiserror::Error)]
+pub enum VaultError {
+    /// The password did not unwrap the vault key. This is also what an
+    /// edited Argon2 parameter or salt in the header looks like, since either
+    /// changes the derived key.
+    #[error("Invalid password. Please check your password and try again.")]
+    WrongPassword,
+    #[error("A file named {name:?} already exists in the vault")]
+    FileExists { name: String },
+    #[error("File not found: {name}")]
+    FileNotFound { name: String },
+    #[error("Invalid file name {name:?}: {reason}")]
+    InvalidName { name: String, reason: String },
+    /// The entry's unlock date, as a Unix time, has not been reached.
+    #[error("File is still locked by time policy")]
+    StillLocked { unlock_at: u64 },
+    /// The vault's delete policy refuses to delete an entry before its
+    /// unlock date.
+    #[error("File is still locked by time policy and this vault does not allow deleting locked files")]
+    DeleteNotAllowed { unlock_at: u64 },
+    /// `set_delete_policy` was asked to relax `UnlockedOnly` while an entry
+    /// is still locked; `unlock_at` is the latest such entry's unlock date.
+    #[error("The delete policy cannot be relaxed until every file has reached its unlock date")]
+    PolicyLocked { unlock_at: u64 },
+    /// Not enough time sources agreed on the current time.
+    #[error("Date and time verification failed: {reason}")]
+    TimeUnavailable { reason: String },
+    #[error("Public time regression detected")]
+    TimeRegression,
+    /// `extend_unlock_date` was given a date before the entry's current one.
+    #[error("The unlock date can only be moved later, never earlier")]
+    UnlockDateEarlier { unlock_at: u64 },
+    /// A puzzle-locked entry was opened before `done` of its `total`
+    /// squarings had been worked through.
+    #[error("Time-lock puzzle is not solved yet")]
+    PuzzleUnsolved { done: u64, total: u64 },
+    /// Authenticated data failed to verify. `entry` names the entry involved
+    /// when it is known.
+    #[error("{reason} - possible tampering detected")]
+    Tampered { entry: Option<String>, reason: String },
+    #[error("Vault is busy - another TimeVault operation is using it{}. Try again once it has finished", .pid.map(|pid| format!(" (process {})", pid)).unwrap_or_default())]
+    Busy { pid: Option<u32> },
+    #[error("A key rotation was interrupted - run rotate-key to finish it")]
+    RekeyPending,
+    /// No vault session is open, or it was closed after being idle.
+    #[error("The vault is not open - enter the password to open it")]
+    NotOpen,
+    /// The vault key was rotated since the session was opened.
+    #[error("The vault key has changed - open the vault again")]
+    SessionExpired,
+    #[error("Vault metadata not found")]
+    VaultNotFound,
+    /// `init_vault` was pointed at a folder that already holds a vault.
+    #[error("A vault already exists in this folder")]
+    VaultExists,
+    #[error("Unsupported vault version {version} - please update TimeVault")]
+    UnsupportedVersion { version: u8 },
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("Vault metadata is corrupt: {reason}")]
+    CorruptMetadata { reason: String },
+    #[error("{message}")]
+    Other { message: String },
+}
+
+impl VaultError {
+    /// The snake_case name of the variant, as serialized in `kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::WrongPassword => "wrong_password",
+            Self::FileExists { .. } => "file_exists",
+            Self::FileNotFound { .. } => "file_not_found",
+            Self::InvalidName { .. } => "invalid_name",
+            Self::StillLocked { .. } => "still_locked",
+            Self::DeleteNotAllowed { .. } => "delete_not_allowed",
+            Self::PolicyLocked { .. } => "policy_locked",
+            Self::TimeUnavailable { .. } => "time_unavailable",
+            Self::TimeRegression => "time_regression",
+            Self::UnlockDateEarlier { .. } => "unlock_date_earlier",
+            Self::PuzzleUnsolved { .. } => "puzzle_unsolved",
+            Self::Tampered { .. } => "tampered",
+            Self::Busy { .. } => "busy",
+            Self::RekeyPending => "rekey_pending",
+            Self::NotOpen => "not_open",
+            Self::SessionExpired => "session_expired",
+            Self::VaultNotFound => "vault_not_found",
+            Self::VaultExists => "vault_exists",
+            Self::UnsupportedVersion { .. } => "unsupported_version",
+            Self::Io(_) => "io",
+            Self::CorruptMetadata { .. } => "corrupt_metadata",
+            Self::Other { .. } => "other",
+        }
+    }
+
+    pub(crate) fn tampered(reason: impl Into<String>) -> Self {
+        Self::Tampered {
+            entry: None,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for VaultError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<VaultError>() {
+            Ok(vault_err) => return vault_err,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<io::Error>() {
+            Ok(io_err) => return Self::Io(io_err),
+            Err(err) => err,
+        };
+        if err.is::<serde_json::Error>() || err.is::<base64::DecodeError>() {
+            return Self::CorruptMetadata { reason: err.to_string() };
+        }
+        Self::Other { message: err.to_string() }
+    }
+}
+
+impl Serialize for VaultError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("kind", self.kind())?;
+        map.serialize_entry("message", &self.to_string())?;
+        match self {
+            Self::FileExists { name } | Self::FileNotFound { name } => map.serialize_entry("name", name)?,
+            Self::InvalidName { name, reason } => {
+                map.serialize_entry("name", name)?;
+                map.serialize_entry("reason", reason)?;
+            }
+            Self::StillLocked { unlock_at } | Self::DeleteNotAllowed { unlock_at } | Self::PolicyLocked { unlock_at } | Self::UnlockDateEarlier { unlock_at } => {
+                map.serialize_entry("unlock_at", unlock_at)?
+            }
+            Self::PuzzleUnsolved { done, total } => {
+                map.serialize_entry("done", done)?;
+                map.serialize_entry("total", total)?;
+            }
+            Self::Tampered { entry, .. } => map.serialize_entry("entry", entry)?,
+            Self::Busy { pid } => map.serialize_entry("pid", pid)?,
+            Self::UnsupportedVersion { version } => map.serialize_entry("version", version)?,
+            _ => {}
+        }
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn conversion_keeps_vault_errors() {
+        let err = VaultError::from(anyhow::Error::from(VaultError::StillLocked { unlock_at: 5 }));
+        assert!(matches!(err, VaultError::StillLocked { unlock_at: 5 }), "{:?}", err);
+
+        let err = VaultError::from(anyhow::Error::from(VaultError::WrongPassword).context("while unlocking"));
+        assert!(matches!(err, VaultError::WrongPassword), "{:?}", err);
+    }
+
+    #[test]
+    fn conversion_classifies_other_errors() {
+        let io = anyhow::Error::from(io::Error::new(io::ErrorKind::NotFound, "gone"));
+        assert_eq!(VaultError::from(io).kind(), "io");
+
+        let json = anyhow::Error::from(serde_json::from_str::<u8>("{").unwrap_err());
+        assert_eq!(VaultError::from(json).kind(), "corrupt_metadata");
+
+        let other = VaultError::from(anyhow!("something else"));
+        assert!(matches!(&other, VaultError::Other { message } if message == "something else"), "{:?}", other);
+    }
+
+    #[test]
+    fn serializes_kind_message_and_fields() {
+        let json = serde_json::to_value(VaultError::FileExists { name: "a.txt".into() }).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "kind": "file_exists",
+                "message": "A file named \"a.txt\" already exists in the vault",
+                "name": "a.txt",
+            })
+        );
+
+        let json = serde_json::to_value(VaultError::Busy { pid: None }).unwrap();
+        assert_eq!(json["kind"], "busy");
+        assert!(json["pid"].is_null());
+        assert_eq!(serde_json::to_value(VaultError::TimeRegression).unwrap().as_object().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,206 @@
+//! Write-ahead journal for operations that change several vault files.
+//!
+//! Adding an entry writes its blob and then its metadata record; deleting one
+//! records the deletion and then removes the record, the blob and any puzzle
+//! progress. Before either starts, the operation is written to `wal.json`,
+//! and the journal is removed once it has completed. If an operation is cut
+//! short, the next time the vault key is opened it is recovered: an add
+//! whose metadata record was never written is rolled back, and a delete is
+//! rolled forward. Individual files are always replaced atomically, so the
+//! journal only has to cover the gaps between them.
+//!
+//! The journal holds nothing secret: blob ids are random. It is not
+//! authenticated either, so recovery trusts nothing in it beyond a
+//! well-formed blob id: a delete is only rolled forward for an entry the
+//! delete policy allows removing.
+
+use anyhow::Result;
+use serde::de::{Deserializer, Error as _};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::error::VaultError;
+use crate::metadata::{is_blob_id, sync_dir, wal_path, write_json_atomic};
+
+/// An operation in progress.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub(crate) enum PendingOp {
+    /// A new entry is being written under `blob_id`.
+    Add {
+        #[serde(deserialize_with = "blob_id")]
+        blob_id: String,
+    },
+    /// The entry stored under `blob_id` is being deleted.
+    Delete {
+        #[serde(deserialize_with = "blob_id")]
+        blob_id: String,
+    },
+}
+
+fn blob_id<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<String, D::Error> {
+    let id = String::deserialize(deserializer)?;
+    if !is_blob_id(&id) {
+        return Err(D::Error::custom(format!("{:?} is not a blob id", id)));
+    }
+    Ok(id)
+}
+
+/// Records `op` as in progress.
+pub(crate) fn begin(vault_path: &Path, op: &PendingOp) -> Result<()> {
+    write_json_atomic(&wal_path(vault_path), op)
+}
+
+/// Returns the operation left in progress, if any. A journal that does not
+/// parse, or names something other than a blob, is
+/// [`VaultError::CorruptMetadata`] and nothing is recovered from it.
+pub(crate) fn pending(vault_path: &Path) -> Result<Option<PendingOp>> {
+    let path = wal_path(vault_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let op = serde_json::from_slice(&fs::read(path)?).map_err(|e| VaultError::CorruptMetadata {
+        reason: format!("unreadable journal: {}", e),
+    })?;
+    Ok(Some(op))
+}
+
+/// Marks the operation in progress as complete.
+pub(crate) fn commit(vault_path: &Path) -> Result<()> {
+    let path = wal_path(vault_path);
+    if path.exists() {
+        fs::remove_file(&path)?;
+        sync_dir(vault_path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{blobs_dir, entry_meta_path, new_blob_id};
+    use crate::metadata::DeletePolicy;
+    use crate::time::{TimePolicy, TimeVerifier};
+    use crate::vault::{add_file, deletion_log, get_status_with_password, init_vault, set_delete_policy_with_verifier, verify_password};
+    use std::path::PathBuf;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    /// A vault holding one entry, `keep.txt`, and that entry's blob id.
+    fn vault_with_entry() -> (tempfile::TempDir, PathBuf, String) {
+        let root = tempfile::tempdir().unwrap();
+        let vault = root.path().join("vault");
+        init_vault(s(&vault), PASSWORD.to_string()).unwrap();
+        let input = root.path().join("keep.txt");
+        fs::write(&input, b"kept").unwrap();
+        add_file(s(&vault), s(&input), PASSWORD.to_string(), 1).unwrap();
+
+        let blob_id = blobs(&vault).pop().unwrap();
+        (root, vault, blob_id)
+    }
+
+    fn s(path: &Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    fn blobs(vault: &Path) -> Vec<String> {
+        fs::read_dir(blobs_dir(vault))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn entry_names(vault: &Path) -> Vec<String> {
+        get_status_with_password(s(vault), PASSWORD.to_string())
+            .unwrap()
+            .iter()
+            .map(|entry| entry["filename"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn rolls_back_an_add_without_metadata() {
+        let (_root, vault, kept) = vault_with_entry();
+        let blob_id = new_blob_id();
+        begin(&vault, &PendingOp::Add { blob_id: blob_id.clone() }).unwrap();
+        fs::write(blobs_dir(&vault).join(&blob_id), b"orphaned ciphertext").unwrap();
+        fs::write(blobs_dir(&vault).join(format!("{}.tmp", blob_id)), b"partial").unwrap();
+
+        verify_password(s(&vault), PASSWORD.to_string()).unwrap();
+        assert_eq!(pending(&vault).unwrap(), None);
+        assert_eq!(blobs(&vault), [kept]);
+        assert_eq!(entry_names(&vault), ["keep.txt"]);
+    }
+
+    #[test]
+    fn keeps_an_add_whose_metadata_was_written() {
+        let (_root, vault, kept) = vault_with_entry();
+        begin(&vault, &PendingOp::Add { blob_id: kept.clone() }).unwrap();
+
+        verify_password(s(&vault), PASSWORD.to_string()).unwrap();
+        assert_eq!(pending(&vault).unwrap(), None);
+        assert_eq!(blobs(&vault), [kept]);
+        assert_eq!(entry_names(&vault), ["keep.txt"]);
+    }
+
+    #[test]
+    fn rolls_forward_a_delete() {
+        let (_root, vault, blob_id) = vault_with_entry();
+        begin(&vault, &PendingOp::Delete { blob_id }).unwrap();
+
+        verify_password(s(&vault), PASSWORD.to_string()).unwrap();
+        assert_eq!(pending(&vault).unwrap(), None);
+        assert!(blobs(&vault).is_empty());
+        assert!(entry_names(&vault).is_empty());
+        let log = deletion_log(s(&vault), PASSWORD.to_string()).unwrap();
+        assert_eq!(log.iter().map(|record| record.filename.as_str()).collect::<Vec<_>>(), ["keep.txt"]);
+    }
+
+    #[test]
+    fn finishes_a_delete_whose_record_is_gone() {
+        let (_root, vault, blob_id) = vault_with_entry();
+        begin(&vault, &PendingOp::Delete { blob_id: blob_id.clone() }).unwrap();
+        fs::remove_file(entry_meta_path(&vault, &blob_id)).unwrap();
+
+        verify_password(s(&vault), PASSWORD.to_string()).unwrap();
+        assert_eq!(pending(&vault).unwrap(), None);
+        assert!(blobs(&vault).is_empty());
+        assert!(entry_names(&vault).is_empty());
+    }
+
+    #[test]
+    fn refuses_a_journal_naming_a_path() {
+        let (root, vault, kept) = vault_with_entry();
+        let outside = root.path().join("outside.txt");
+        fs::write(&outside, b"not part of the vault").unwrap();
+        fs::write(root.path().join("outside.txt.meta.json"), b"not part of the vault").unwrap();
+
+        for op in ["add", "delete"] {
+            for blob_id in ["../../outside.txt", &s(&outside), &kept.to_uppercase()] {
+                fs::write(wal_path(&vault), serde_json::json!({ "op": op, "blob_id": blob_id }).to_string()).unwrap();
+
+                let err = VaultError::from(verify_password(s(&vault), PASSWORD.to_string()).unwrap_err());
+                assert!(matches!(err, VaultError::CorruptMetadata { .. }), "{} {}: {:?}", op, blob_id, err);
+                assert!(outside.exists());
+                assert!(root.path().join("outside.txt.meta.json").exists());
+                assert_eq!(blobs(&vault), std::slice::from_ref(&kept));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn ignores_a_delete_the_policy_forbids() {
+        let (_root, vault, blob_id) = vault_with_entry();
+        let no_clock = TimeVerifier::new(vec![], TimePolicy::default());
+        set_delete_policy_with_verifier(s(&vault), PASSWORD.to_string(), DeletePolicy::UnlockedOnly, &no_clock).await.unwrap();
+
+        // No time has been verified yet, so keep.txt is still protected.
+        begin(&vault, &PendingOp::Delete { blob_id: blob_id.clone() }).unwrap();
+        verify_password(s(&vault), PASSWORD.to_string()).unwrap();
+        assert_eq!(pending(&vault).unwrap(), None);
+        assert_eq!(blobs(&vault), [blob_id]);
+        assert_eq!(entry_names(&vault), ["keep.txt"]);
+        assert!(deletion_log(s(&vault), PASSWORD.to_string()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+//! Core library behind TimeVault.
+//!
+//! A vault is a directory holding `vault_metadata.json`, a `blobs/` directory
+//! with one encrypted blob per entry and a `files_meta/` directory of
+//! encrypted per-entry metadata (see [`metadata`] for the layout).
+//! Entries are encrypted in chunks with XChaCha20-Poly1305, each under its own
+//! random data key. Data keys and entry metadata are encrypted under a random
+//! file encryption key (FEK), which is itself wrapped by a key derived from
+//! the vault password with Argon2id; the FEK can be replaced with
+//! [`rotate_fek`]. Entries only decrypt once several
+//! independent time sources, including at least one authenticated one such
+//! as a signed Roughtime response, agree that their unlock date has passed.
+//! Entries can additionally be time-lock encrypted to a drand round (see
+//! [`tlock`]), which makes early decryption impossible rather than refused.
+//!
+//! This crate has no Tauri dependency; the desktop app and other tooling call
+//! the functions re-exported here. Each takes the password and derives the
+//! key anew; long-lived callers can instead [`open_vault`] once and run
+//! operations on the returned [`VaultSession`].
+
+pub mod crypto;
+pub mod error;
+mod journal;
+mod lock;
+pub mod metadata;
+pub mod migrate;
+pub mod name;
+pub mod puzzle;
+pub mod rekey;
+pub mod session;
+pub mod stream;
+pub mod time;
+pub mod tlock;
+pub mod vault;
+
+pub use crypto::{calibrate_argon_params, default_argon_params, derive_key};
+pub use error::VaultError;
+pub use metadata::{
+    ArgonCalibration, DeletePolicy, DeletionRecord, EncryptedFileMeta, FileMetaPayload, VaultInfo, VaultMetadata, WrappedKey,
+    VAULT_VERSION,
+};
+pub use migrate::migrate_vault;
+pub use name::EntryName;
+pub use rekey::rotate_fek;
+pub use session::{open_vault, VaultSession};
+pub use time::{
+    fetch_public_unixtime_with_retries, fetch_verified_unixtime, RoughtimeClient, RoughtimeServer, SntpSource, TimePolicy,
+    TimeSample, TimeSource, TimeVerifier,
+};
+pub use vault::{
+    add_file, add_file_puzzle_locked, add_file_time_locked, add_file_with_name, change_password, delete_entry,
+    delete_entry_with_verifier, deletion_log, extend_unlock_date, get_status_with_password, init_vault, refresh_server_time,
+    refresh_server_time_with_verifier, rename_entry, set_delete_policy, set_delete_policy_with_verifier, solve_puzzle, unlock_file,
+    unlock_file_with_verifier, unlock_vault, unlock_vault_with_verifier, upgrade_argon_params, verify_password, vault_info,
+};
@@ -0,0 +1,131 @@
+//! Cross-process locking of a vault.
+//!
+//! Every operation that opens the vault key or changes vault files holds an
+//! exclusive advisory lock (`flock` on Unix, `LockFileEx` on Windows) on
+//! `vault.lock` for its whole duration, so two windows of the app, or the
+//! app and a script, cannot interleave read-modify-write cycles on the same
+//! vault. The lock belongs to the open file, so the operating system releases
+//! it when the holder exits or crashes. A lock file left behind by such a
+//! holder is stale: its lock is no longer held and the next operation takes
+//! it over.
+//!
+//! The lock file records the holder's process id and start time, which are
+//! only used to explain a "vault is busy" error. It is only created inside an
+//! existing vault, or by `init_vault` for a new one, so pointing an operation
+//! at the wrong folder leaves nothing behind.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::error::VaultError;
+use crate::metadata::{lock_path, vault_meta_path};
+
+/// How long to keep retrying a lock held by another operation.
+const LOCK_WAIT: Duration = Duration::from_secs(2);
+const RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Who holds the lock, as written to the lock file.
+#[derive(Serialize, Deserialize)]
+struct LockOwner {
+    pid: u32,
+    acquired_at: u64,
+}
+
+/// An exclusive lock on a vault, released when dropped.
+pub(crate) struct VaultLock {
+    file: File,
+}
+
+impl VaultLock {
+    /// Locks the vault at `vault_path`, waiting briefly if another operation
+    /// holds it. Fails with [`VaultError::VaultNotFound`] if there is no vault
+    /// there, and with [`VaultError::Busy`] if the lock is still held after
+    /// the wait.
+    pub(crate) fn acquire(vault_path: &Path) -> Result<Self> {
+        if !vault_meta_path(vault_path).exists() {
+            return Err(VaultError::VaultNotFound.into());
+        }
+        Self::acquire_new(vault_path)
+    }
+
+    /// Like [`acquire`](Self::acquire), but for a vault that is about to be
+    /// created in `vault_path`.
+    pub(crate) fn acquire_new(vault_path: &Path) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(lock_path(vault_path))?;
+
+        let started = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_WAIT => sleep(RETRY_INTERVAL),
+                Err(TryLockError::WouldBlock) => {
+                    let pid = read_owner(&mut file).map(|owner| owner.pid);
+                    return Err(VaultError::Busy { pid }.into());
+                }
+                Err(TryLockError::Error(e)) => return Err(anyhow!("Could not lock the vault: {}", e)),
+            }
+        }
+
+        // Whatever the file says now describes a holder that is gone.
+        let owner = LockOwner {
+            pid: std::process::id(),
+            acquired_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(&serde_json::to_vec(&owner)?)?;
+        Ok(Self { file })
+    }
+}
+
+fn read_owner(file: &mut File) -> Option<LockOwner> {
+    let mut raw = vec![];
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_end(&mut raw).ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+impl Drop for VaultLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn second_acquire_reports_the_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(vault_meta_path(dir.path()), b"{}").unwrap();
+
+        let held = VaultLock::acquire(dir.path()).unwrap();
+        let started = Instant::now();
+        let err = VaultError::from(VaultLock::acquire(dir.path()).err().unwrap());
+        assert!(matches!(err, VaultError::Busy { pid: Some(pid) } if pid == std::process::id()), "{:?}", err);
+        assert!(started.elapsed() >= LOCK_WAIT);
+
+        drop(held);
+        VaultLock::acquire(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn refuses_a_folder_without_a_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = VaultError::from(VaultLock::acquire(dir.path()).err().unwrap());
+        assert!(matches!(err, VaultError::VaultNotFound), "{:?}", err);
+        assert!(!lock_path(dir.path()).exists());
+    }
+}
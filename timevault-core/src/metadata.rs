@@ -0,0 +1,343 @@
+//! On-disk metadata formats and vault directory layout.
+//!
+//! ```text
+//! vault_dir/
+//! ├── vault_metadata.json     VaultMetadata
+//! ├── blobs/<blob_id>         entry ciphertext
+//! ├── files_meta/<blob_id>.meta.json
+//! │                           EncryptedFileMeta
+//! ├── puzzles/<blob_id>.json  PuzzleProgress of a time-lock puzzle solver
+//! ├── deletions.json          DeletionRecords, each an EncryptedFileMeta
+//! ├── wal.json                add or delete in progress (see journal)
+//! ├── vault.lock              held by the operation using the vault (see lock)
+//! ├── rekey_journal.json      new key of an interrupted key rotation
+//! └── rekey_moves.jsonl       entries moved to new blobs by that rotation
+//! ```
+//!
+//! Blob and metadata file names are random and reveal nothing about the
+//! entry; the entry name lives only inside the encrypted payload.
+
+use anyhow::{anyhow, Result};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::crypto::check_argon_params;
+use crate::error::VaultError;
+use crate::puzzle::RswPuzzle;
+use crate::tlock::TimeLock;
+
+/// Current [`VaultMetadata::version`]. Older vaults are upgraded by
+/// [`migrate`](crate::migrate) the next time they are unlocked.
+///
+/// - 1: entry payloads carry a copy of the ciphertext; blobs may still be
+///   named `.locked_<filename>`.
+/// - 2: payloads hold only descriptive fields and blobs live under `blobs/`.
+/// - 3: the header is authenticated by `state_mac_b64` and the wrapped FEK is
+///   bound to this version.
+pub const VAULT_VERSION: u8 = 3;
+
+/// Vault-wide header stored in `vault_metadata.json`.
+///
+/// Holds the Argon2 parameters and salt used to derive the wrapping key, the
+/// wrapped file encryption key (FEK), and the last verified public time used
+/// for rollback detection.
+///
+/// `authenticated_time` is the rollback watermark and, together with every
+/// other field except `last_verified_time`, is covered by `state_mac_b64`, an
+/// HMAC under a key derived from the FEK. `last_verified_time` is an
+/// unauthenticated copy that [`refresh_server_time`](crate::refresh_server_time)
+/// can update without the password; it may only ever raise the watermark.
+/// The MAC can only be checked once the FEK is unwrapped, so edits to the
+/// Argon2 parameters or salt are reported as a wrong password instead.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct VaultMetadata {
+    pub version: u8,
+    pub salt_b64: String,
+    pub argon_mem_kib: u32,
+    pub argon_iters: u32,
+    pub argon_parallelism: u32,
+    pub wrapped_fek_b64: String,
+    pub wrap_nonce_b64: String,
+    pub creation_ts: u64,
+    pub last_verified_time: u64,
+    #[serde(default)]
+    pub authenticated_time: u64,
+    #[serde(default)]
+    pub state_mac_b64: String,
+    /// How the current Argon2 parameters were chosen, if they came from
+    /// [`upgrade_argon_params`](crate::upgrade_argon_params) rather than the
+    /// defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub argon_calibration: Option<ArgonCalibration>,
+    #[serde(default, skip_serializing_if = "DeletePolicy::is_any")]
+    pub delete_policy: DeletePolicy,
+}
+
+/// Which entries [`delete_entry`](crate::delete_entry) may remove, set with
+/// [`set_delete_policy`](crate::set_delete_policy).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletePolicy {
+    /// Any entry.
+    #[default]
+    Any,
+    /// Only entries whose unlock date has passed according to verified time.
+    UnlockedOnly,
+}
+
+impl DeletePolicy {
+    pub fn is_any(&self) -> bool {
+        *self == DeletePolicy::Any
+    }
+}
+
+/// Result of benchmarking Argon2 on the machine that last upgraded a vault's
+/// key-derivation parameters.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ArgonCalibration {
+    pub mem_kib: u32,
+    pub iters: u32,
+    pub parallelism: u32,
+    /// Unlock latency the parameters were chosen to hit, in milliseconds.
+    pub target_ms: u64,
+    /// Measured key-derivation time with the chosen parameters.
+    pub measured_ms: u64,
+    /// Unix time of the calibration.
+    pub calibrated_at: u64,
+}
+
+impl VaultMetadata {
+    /// Earliest public time the vault will accept without reporting a
+    /// regression.
+    pub fn time_watermark(&self) -> u64 {
+        self.authenticated_time.max(self.last_verified_time)
+    }
+}
+
+/// Per-entry record stored under `files_meta/`, holding a [`FileMetaPayload`]
+/// encrypted under the FEK.
+#[derive(Serialize, Deserialize)]
+pub struct EncryptedFileMeta {
+    pub encrypted_payload_b64: String,
+    pub metadata_nonce_b64: String,
+}
+
+/// Decrypted contents of an [`EncryptedFileMeta`] record.
+///
+/// Holds only descriptive fields and the entry's key material; the
+/// ciphertext itself lives in the blob named by `blob_id`. `content_sha256`
+/// is the hex SHA-256 of the plaintext and is checked after decryption.
+///
+/// Each entry's content is encrypted under its own random key: `data_key`
+/// wraps it under the FEK, while `time_lock` and `puzzle` seal it to a
+/// future time instead. Entries written before per-entry keys carry none of
+/// the three and are encrypted with the FEK itself.
+///
+/// When `chunk_size` is set the blob uses the chunked [`stream`](crate::stream)
+/// format and `nonce_b64` holds the stream's nonce prefix; otherwise the blob
+/// is a single XChaCha20-Poly1305 ciphertext under `nonce_b64`, as written
+/// before chunked encryption was introduced.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileMetaPayload {
+    pub filename: String,
+    pub file_size: u64,
+    pub file_unlock_date: u64,
+    pub content_sha256: String,
+    pub blob_id: String,
+    pub nonce_b64: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<u32>,
+    /// The entry's data key wrapped by the FEK.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_key: Option<WrappedKey>,
+    /// Set for entries whose content key is time-lock encrypted to a drand
+    /// round rather than being the FEK.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_lock: Option<TimeLock>,
+    /// Set for entries whose content key is wrapped in a sequential-squaring
+    /// time-lock puzzle rather than being the FEK.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub puzzle: Option<RswPuzzle>,
+}
+
+/// An entry removed by [`delete_entry`](crate::delete_entry), kept encrypted
+/// under the FEK in `deletions.json`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DeletionRecord {
+    pub filename: String,
+    pub blob_id: String,
+    pub file_unlock_date: u64,
+    pub content_sha256: String,
+    /// Unix time of the deletion, from the local clock.
+    pub deleted_at: u64,
+}
+
+/// A per-entry data key encrypted under the FEK and bound to the entry's
+/// blob id, so it cannot be moved to another entry.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WrappedKey {
+    pub nonce_b64: String,
+    pub wrapped_key_b64: String,
+}
+
+/// Public, non-secret summary of a vault as returned by
+/// [`vault_info`](crate::vault_info) and
+/// [`refresh_server_time`](crate::refresh_server_time).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VaultInfo {
+    pub created: u64,
+    pub last_server_time: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_source: Option<String>,
+}
+
+pub(crate) fn vault_meta_path(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("vault_metadata.json")
+}
+
+pub(crate) fn files_meta_dir(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("files_meta")
+}
+
+pub(crate) fn blobs_dir(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("blobs")
+}
+
+pub(crate) fn blob_path(vault_dir: &Path, payload: &FileMetaPayload) -> PathBuf {
+    blobs_dir(vault_dir).join(&payload.blob_id)
+}
+
+pub(crate) fn legacy_blob_path(vault_dir: &Path, filename: &str) -> PathBuf {
+    vault_dir.join(format!(".locked_{}", filename))
+}
+
+pub(crate) fn entry_meta_path(vault_dir: &Path, blob_id: &str) -> PathBuf {
+    files_meta_dir(vault_dir).join(format!("{}.meta.json", blob_id))
+}
+
+/// Whether `path` is an entry metadata record, as opposed to a temporary
+/// file left behind by an interrupted write.
+pub(crate) fn is_entry_meta_file(path: &Path) -> bool {
+    path.is_file() && path.file_name().is_some_and(|name| name.to_string_lossy().ends_with(".meta.json"))
+}
+
+pub(crate) fn rekey_journal_path(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("rekey_journal.json")
+}
+
+pub(crate) fn rekey_moves_path(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("rekey_moves.jsonl")
+}
+
+pub(crate) fn lock_path(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("vault.lock")
+}
+
+pub(crate) fn wal_path(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("wal.json")
+}
+
+pub(crate) fn deletion_log_path(vault_dir: &Path) -> PathBuf {
+    vault_dir.join("deletions.json")
+}
+
+pub(crate) fn puzzle_progress_path(vault_dir: &Path, blob_id: &str) -> PathBuf {
+    vault_dir.join("puzzles").join(format!("{}.json", blob_id))
+}
+
+/// Generates a random opaque identifier for a new blob.
+pub(crate) fn new_blob_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+/// Whether `id` has the form [`new_blob_id`] gives: 32 lowercase hex digits.
+///
+/// Blob ids read from unauthenticated files are checked with this before
+/// they are joined into a path, so they cannot name anything outside the
+/// vault's own directories.
+pub(crate) fn is_blob_id(id: &str) -> bool {
+    id.len() == 32 && id.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+pub(crate) fn ensure_vault_dir(path: &Path) -> Result<()> {
+    if !path.exists() {
+        fs::create_dir_all(path)?;
+    }
+    Ok(())
+}
+
+/// Parses the contents of `vault_metadata.json`.
+///
+/// Only the structure is checked here; the header's authenticity is checked
+/// with the FEK once it has been unwrapped. Fails with
+/// [`VaultError::UnsupportedVersion`] for a newer vault, and with
+/// [`VaultError::CorruptMetadata`] for a version or Argon2 parameters no
+/// vault could have, so that a doctored header cannot make key derivation
+/// attempt an enormous allocation or run for hours.
+pub fn parse_vault_metadata(raw: &[u8]) -> Result<VaultMetadata> {
+    let meta: VaultMetadata = serde_json::from_slice(raw)?;
+    if meta.version > VAULT_VERSION {
+        return Err(VaultError::UnsupportedVersion { version: meta.version }.into());
+    }
+    if meta.version == 0 {
+        return Err(VaultError::CorruptMetadata {
+            reason: "version 0 does not exist".to_string(),
+        }
+        .into());
+    }
+    check_argon_params(meta.argon_mem_kib, meta.argon_iters, meta.argon_parallelism)?;
+    Ok(meta)
+}
+
+pub(crate) fn read_vault_metadata(vault_dir: &Path) -> Result<VaultMetadata> {
+    parse_vault_metadata(&fs::read(vault_meta_path(vault_dir))?)
+}
+
+/// Reads the encrypted records of `deletions.json`, which may not exist yet.
+pub(crate) fn read_deletion_log(vault_dir: &Path) -> Result<Vec<EncryptedFileMeta>> {
+    let path = deletion_log_path(vault_dir);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Replaces `vault_metadata.json` atomically, so a crash leaves either the
+/// old or the new header, never a torn one.
+pub(crate) fn write_vault_metadata(vault_dir: &Path, meta: &VaultMetadata) -> Result<()> {
+    write_json_atomic(&vault_meta_path(vault_dir), meta)
+}
+
+/// Writes `value` as JSON to `path` atomically: it is written and synced to a
+/// temporary file which is then renamed over `path`, and the directory is
+/// synced so the rename itself is durable.
+pub(crate) fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let file_name = path.file_name().ok_or_else(|| anyhow!("bad filename"))?;
+    let tmp = path.with_file_name(format!("{}.tmp", file_name.to_string_lossy()));
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(&serde_json::to_vec_pretty(value)?)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    if let Some(dir) = path.parent() {
+        sync_dir(dir)?;
+    }
+    Ok(())
+}
+
+/// Makes renames and removals in `dir` durable. A no-op where directories
+/// cannot be synced.
+pub(crate) fn sync_dir(dir: &Path) -> Result<()> {
+    #[cfg(unix)]
+    fs::File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
@@ -0,0 +1,161 @@
+//! Upgrades of vaults written by earlier versions.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::crypto::{
+    decrypt_file_metadata_bytes, derive_key, encrypt_file_metadata, seal_vault_state, sha256_hex, unwrap_fek, wrap_fek,
+};
+use crate::lock::VaultLock;
+use crate::metadata::{
+    blobs_dir, entry_meta_path, files_meta_dir, is_entry_meta_file, legacy_blob_path, new_blob_id, read_vault_metadata,
+    write_json_atomic, write_vault_metadata, EncryptedFileMeta, FileMetaPayload, VaultMetadata, VAULT_VERSION,
+};
+
+/// Entry payload as written by version-1 vaults.
+#[derive(Deserialize)]
+struct PayloadV1 {
+    filename: String,
+    file_unlock_date: u64,
+    nonce_b64: String,
+    #[serde(default)]
+    blob_id: Option<String>,
+}
+
+/// Upgrades the vault at `vault_path` to [`VAULT_VERSION`] if it is older,
+/// recording the new version in `meta`. Returns the number of entries
+/// rewritten.
+///
+/// Moving to version 3 re-wraps the FEK, so the password is needed to
+/// re-derive the wrapping key.
+pub(crate) fn upgrade_vault(vault_path: &Path, meta: &mut VaultMetadata, fek: &[u8; 32], password: &str) -> Result<usize> {
+    if meta.version >= VAULT_VERSION {
+        return Ok(0);
+    }
+
+    let mut migrated = 0;
+    if meta.version < 2 {
+        migrated = migrate_v1_entries(vault_path, fek)?;
+    }
+
+    let salt = general_purpose::STANDARD.decode(&meta.salt_b64)?;
+    let mut derived = derive_key(password, &salt, meta.argon_mem_kib, meta.argon_iters, meta.argon_parallelism)?;
+    let wrapped = wrap_fek(&derived, fek);
+    derived.zeroize();
+    (meta.wrapped_fek_b64, meta.wrap_nonce_b64) = wrapped?;
+
+    meta.version = VAULT_VERSION;
+    meta.authenticated_time = meta.last_verified_time;
+    seal_vault_state(fek, meta)?;
+    write_vault_metadata(vault_path, meta)?;
+    Ok(migrated)
+}
+
+/// Rewrites version-1 entries into the version-2 layout.
+///
+/// Each entry's blob is moved to an opaque id under `blobs/` and its payload
+/// is replaced by one without the embedded ciphertext, with the plaintext
+/// size and hash filled in. The new metadata record is written before the
+/// blob is moved and the old record removed last, so an interruption never
+/// leaves an entry without a readable record; entries already in the new
+/// format are skipped when the migration is resumed.
+fn migrate_v1_entries(vault_path: &Path, fek: &[u8; 32]) -> Result<usize> {
+    let fm_dir = files_meta_dir(vault_path);
+    if !fm_dir.exists() {
+        return Ok(0);
+    }
+    fs::create_dir_all(blobs_dir(vault_path))?;
+    let aead_fek = XChaCha20Poly1305::new(Key::from_slice(fek));
+
+    let mut migrated = 0;
+    for entry in fs::read_dir(&fm_dir)? {
+        let path = entry?.path();
+        if !is_entry_meta_file(&path) {
+            continue;
+        }
+        let raw = fs::read(&path)?;
+        let Ok(encrypted_meta) = serde_json::from_slice::<EncryptedFileMeta>(&raw) else {
+            continue;
+        };
+        let Ok(decrypted) = decrypt_file_metadata_bytes(fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64) else {
+            continue;
+        };
+        if serde_json::from_slice::<FileMetaPayload>(&decrypted).is_ok() {
+            continue;
+        }
+        let old: PayloadV1 = serde_json::from_slice(&decrypted)?;
+
+        let old_blob = match &old.blob_id {
+            Some(id) => blobs_dir(vault_path).join(id),
+            None => legacy_blob_path(vault_path, &old.filename),
+        };
+        if !old_blob.exists() {
+            continue;
+        }
+
+        let ciphertext = fs::read(&old_blob)?;
+        let nonce_bytes = general_purpose::STANDARD.decode(&old.nonce_b64)?;
+        if nonce_bytes.len() != 24 {
+            return Err(anyhow!("Invalid nonce length"));
+        }
+        let mut plaintext = aead_fek
+            .decrypt(XNonce::from_slice(&nonce_bytes), ciphertext.as_ref())
+            .map_err(|_| anyhow!("Decryption failed while migrating an entry"))?;
+
+        let blob_id = old.blob_id.clone().unwrap_or_else(new_blob_id);
+        let payload = FileMetaPayload {
+            filename: old.filename,
+            file_size: plaintext.len() as u64,
+            file_unlock_date: old.file_unlock_date,
+            content_sha256: sha256_hex(&plaintext),
+            blob_id: blob_id.clone(),
+            nonce_b64: old.nonce_b64,
+            chunk_size: None,
+            data_key: None,
+            time_lock: None,
+            puzzle: None,
+        };
+        plaintext.zeroize();
+
+        let (encrypted_payload_b64, metadata_nonce_b64) = encrypt_file_metadata(fek, &payload)?;
+        let file_meta = EncryptedFileMeta {
+            encrypted_payload_b64,
+            metadata_nonce_b64,
+        };
+        let new_meta_path = entry_meta_path(vault_path, &blob_id);
+        write_json_atomic(&new_meta_path, &file_meta)?;
+
+        let new_blob = blobs_dir(vault_path).join(&blob_id);
+        if old_blob != new_blob {
+            fs::rename(&old_blob, &new_blob)?;
+        }
+        if path != new_meta_path {
+            fs::remove_file(&path)?;
+        }
+        migrated += 1;
+    }
+
+    Ok(migrated)
+}
+
+/// Brings the vault at `vault_dir` up to the current on-disk format.
+///
+/// Vault operations that unlock the key run this automatically; it is exposed
+/// for tooling that wants to migrate eagerly. Returns the number of entries
+/// rewritten.
+pub fn migrate_vault(vault_dir: String, password: String) -> Result<usize> {
+    let vault_path = Path::new(&vault_dir);
+    let _lock = VaultLock::acquire(vault_path)?;
+    let mut meta = read_vault_metadata(vault_path)?;
+    let mut fek_arr = unwrap_fek(&meta, &password)?;
+    let result = upgrade_vault(vault_path, &mut meta, &fek_arr, &password);
+    fek_arr.zeroize();
+    result
+}
+
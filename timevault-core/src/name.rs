@@ -0,0 +1,131 @@
+//! Validated entry names.
+//!
+//! An entry's name becomes the name of the file it is decrypted to, joined
+//! onto the caller's output directory. Names come from the user at add time
+//! but also from metadata that may have been crafted by an attacker, so they
+//! are checked on the way in and again before anything is written.
+
+use anyhow::Result;
+use std::fmt;
+use std::path::Path;
+
+use crate::error::VaultError;
+
+/// Longest accepted name in bytes, the common file-system limit.
+const MAX_LEN: usize = 255;
+
+/// Characters that are path separators or otherwise invalid in Windows file
+/// names.
+const FORBIDDEN_CHARS: &[char] = &['/', '\\', ':', '<', '>', '"', '|', '?', '*'];
+
+/// Device names Windows reserves in every directory, with or without an
+/// extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4",
+    "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// An entry name that is a single, portable path component and so cannot
+/// escape the directory it is joined onto.
+///
+/// Rejects empty names, `.` and `..`, path separators and drive prefixes,
+/// control characters, Windows reserved device names, and names ending in a
+/// dot or space (which Windows silently strips).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EntryName(String);
+
+impl EntryName {
+    pub fn new(name: &str) -> Result<Self> {
+        validate(name).map_err(|reason| VaultError::InvalidName {
+            name: name.to_string(),
+            reason: reason.to_string(),
+        })?;
+        Ok(Self(name.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+fn validate(name: &str) -> std::result::Result<(), &'static str> {
+    if name.is_empty() {
+        return Err("name is empty");
+    }
+    if name.len() > MAX_LEN {
+        return Err("name is too long");
+    }
+    if name == "." || name == ".." {
+        return Err("name refers to a directory");
+    }
+    if name.chars().any(|c| FORBIDDEN_CHARS.contains(&c)) {
+        return Err("name contains a path separator or reserved character");
+    }
+    if name.chars().any(char::is_control) {
+        return Err("name contains a control character");
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err("name ends with a dot or space");
+    }
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved)) {
+        return Err("name is reserved by Windows");
+    }
+    Ok(())
+}
+
+impl fmt::Display for EntryName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for EntryName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for EntryName {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.0)
+    }
+}
+
+impl TryFrom<String> for EntryName {
+    type Error = anyhow::Error;
+
+    fn try_from(name: String) -> Result<Self> {
+        Self::new(&name)
+    }
+}
+
+impl From<EntryName> for String {
+    fn from(name: EntryName) -> Self {
+        name.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_ordinary_names() {
+        for name in ["notes.txt", "Report 2024.pdf", ".hidden", "archive.tar.gz", "résumé.docx", "CONSOLE.txt", "nul-terminated"] {
+            assert!(EntryName::new(name).is_ok(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_names_that_escape_or_are_not_portable() {
+        let too_long = "a".repeat(MAX_LEN + 1);
+        for name in [
+            "", ".", "..", "../x", "a/b", "a\\b", "C:x", "x\0y", "tab\there", "trailing.", "trailing ", "CON", "nul.txt", "Com1.log",
+            "LPT9 .txt", too_long.as_str(),
+        ] {
+            let err = VaultError::from(EntryName::new(name).unwrap_err());
+            assert!(matches!(&err, VaultError::InvalidName { name: n, .. } if n == name), "{:?}: {:?}", name, err);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+//! Rivest–Shamir–Wagner time-lock puzzles.
+//!
+//! An entry's content key is encrypted under a key derived from
+//! `a^(2^t) mod n`, where `n = p·q` is an RSA modulus whose factors are
+//! discarded once the puzzle is built. Knowing `φ(n)` the creator computes
+//! the answer instantly; without it the only known method is `t` sequential
+//! modular squarings, which cannot be parallelised. `t` is calibrated from
+//! this machine's squaring speed so that solving takes roughly the chosen
+//! wall-clock time, with no network or trusted clock involved.
+//!
+//! The guarantee is only as good as the calibration: a machine with faster
+//! single-core modular arithmetic solves the puzzle proportionally sooner.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use num_bigint_dig::{BigUint, RandBigInt, RandPrime};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+use crate::error::VaultError;
+
+/// Bit length of each prime factor of the puzzle modulus.
+const PRIME_BITS: usize = 1024;
+const KEY_WRAP_AAD: &[u8] = b"timevault rsw puzzle v1";
+const CALIBRATION_TIME: Duration = Duration::from_millis(1500);
+
+/// A time-lock puzzle wrapping an entry's content key, stored in its
+/// metadata.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RswPuzzle {
+    /// Hex-encoded modulus `n`.
+    pub modulus_hex: String,
+    /// Hex-encoded base `a`.
+    pub base_hex: String,
+    /// Number of sequential squarings `t`.
+    pub squarings: u64,
+    /// Squarings per second measured when the puzzle was built.
+    pub calibrated_rate: u64,
+    pub nonce_b64: String,
+    /// The content key encrypted under a key derived from `a^(2^t) mod n`.
+    pub wrapped_key_b64: String,
+}
+
+/// Progress of a solver, saved so that solving can resume after a restart.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PuzzleProgress {
+    /// Identifies the puzzle this progress belongs to.
+    pub puzzle_id: String,
+    /// Squarings performed so far.
+    pub done: u64,
+    /// Hex-encoded `a^(2^done) mod n`.
+    pub value_hex: String,
+}
+
+impl RswPuzzle {
+    fn modulus(&self) -> BigUint {
+        BigUint::parse_bytes(self.modulus_hex.as_bytes(), 16).unwrap_or_default()
+    }
+
+    /// Stable identifier of this puzzle, used to match saved progress.
+    pub fn id(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.modulus_hex.as_bytes());
+        hasher.update(self.base_hex.as_bytes());
+        hasher.update(self.squarings.to_be_bytes());
+        hex::encode(&hasher.finalize()[..16])
+    }
+
+    /// Progress before any squaring has been done.
+    pub fn start(&self) -> PuzzleProgress {
+        PuzzleProgress {
+            puzzle_id: self.id(),
+            done: 0,
+            value_hex: self.base_hex.clone(),
+        }
+    }
+
+    /// Performs up to `max_steps` further squarings on `progress`. Returns
+    /// `true` once the puzzle is solved.
+    pub fn advance(&self, progress: &mut PuzzleProgress, max_steps: u64) -> Result<bool> {
+        if progress.puzzle_id != self.id() {
+            return Err(anyhow!("Saved puzzle progress belongs to a different puzzle"));
+        }
+        let modulus = self.modulus();
+        if modulus.bits() < 2 {
+            return Err(anyhow!("Invalid puzzle modulus"));
+        }
+        let mut value = BigUint::parse_bytes(progress.value_hex.as_bytes(), 16)
+            .ok_or_else(|| anyhow!("Invalid saved puzzle progress"))?;
+
+        let steps = max_steps.min(self.squarings.saturating_sub(progress.done));
+        for _ in 0..steps {
+            value = &value * &value % &modulus;
+        }
+        progress.done += steps;
+        progress.value_hex = value.to_str_radix(16);
+        Ok(progress.done >= self.squarings)
+    }
+
+    /// Recovers the content key from a solved puzzle's progress.
+    pub fn open_key(&self, progress: &PuzzleProgress) -> Result<[u8; 32]> {
+        if progress.puzzle_id != self.id() || progress.done < self.squarings {
+            let done = if progress.puzzle_id == self.id() { progress.done } else { 0 };
+            return Err(VaultError::PuzzleUnsolved {
+                done,
+                total: self.squarings,
+            }
+            .into());
+        }
+        let value = BigUint::parse_bytes(progress.value_hex.as_bytes(), 16)
+            .ok_or_else(|| anyhow!("Invalid saved puzzle progress"))?;
+        let mut wrap_key = solution_key(&value);
+
+        let nonce = general_purpose::STANDARD.decode(&self.nonce_b64)?;
+        let wrapped = general_purpose::STANDARD.decode(&self.wrapped_key_b64)?;
+        if nonce.len() != 24 {
+            return Err(anyhow!("Invalid nonce length"));
+        }
+        let plain = XChaCha20Poly1305::new(Key::from_slice(&wrap_key))
+            .decrypt(XNonce::from_slice(&nonce), Payload { msg: &wrapped, aad: KEY_WRAP_AAD })
+            .map_err(|_| VaultError::tampered("Time-lock puzzle solution is wrong"));
+        wrap_key.zeroize();
+        let mut plain = plain?;
+
+        let key = <[u8; 32]>::try_from(plain.as_slice()).map_err(|_| anyhow!("Invalid puzzle key length"));
+        plain.zeroize();
+        key
+    }
+}
+
+fn solution_key(value: &BigUint) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"timevault rsw solution");
+    hasher.update(value.to_bytes_be());
+    hasher.finalize().into()
+}
+
+/// Measures how many modular squarings per second this machine performs
+/// for a modulus of the size puzzles use.
+pub fn calibrate() -> u64 {
+    let modulus = OsRng.gen_biguint(2 * PRIME_BITS) | (BigUint::from(1u32) << (2 * PRIME_BITS - 1)) | BigUint::from(1u32);
+    let mut value = OsRng.gen_biguint_below(&modulus);
+
+    let started = Instant::now();
+    let mut count = 0u64;
+    while started.elapsed() < CALIBRATION_TIME {
+        for _ in 0..1000 {
+            value = &value * &value % &modulus;
+        }
+        count += 1000;
+    }
+    (count as f64 / started.elapsed().as_secs_f64()) as u64
+}
+
+/// Builds a puzzle around `key` that takes about `delay_secs` of sequential
+/// computation at `squarings_per_sec` to solve.
+pub fn seal_key(key: &[u8; 32], delay_secs: u64, squarings_per_sec: u64) -> Result<RswPuzzle> {
+    let squarings = delay_secs
+        .checked_mul(squarings_per_sec)
+        .ok_or_else(|| anyhow!("Puzzle delay is too long"))?
+        .max(1);
+
+    let p = OsRng.gen_prime(PRIME_BITS);
+    let q = OsRng.gen_prime(PRIME_BITS);
+    let modulus = &p * &q;
+    let phi = (&p - 1u32) * (&q - 1u32);
+    let base = OsRng.gen_biguint_range(&BigUint::from(2u32), &modulus);
+
+    // The shortcut: a^(2^t) = a^(2^t mod φ(n)) mod n.
+    let exponent = BigUint::from(2u32).modpow(&BigUint::from(squarings), &phi);
+    let value = base.modpow(&exponent, &modulus);
+    let mut wrap_key = solution_key(&value);
+
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+    let wrapped = XChaCha20Poly1305::new(Key::from_slice(&wrap_key))
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: key, aad: KEY_WRAP_AAD })
+        .map_err(|_| anyhow!("Encryption failed"));
+    wrap_key.zeroize();
+
+    Ok(RswPuzzle {
+        modulus_hex: modulus.to_str_radix(16),
+        base_hex: base.to_str_radix(16),
+        squarings,
+        calibrated_rate: squarings_per_sec,
+        nonce_b64: general_purpose::STANDARD.encode(nonce),
+        wrapped_key_b64: general_purpose::STANDARD.encode(wrapped?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    const KEY: [u8; 32] = [0x3c; 32];
+
+    /// A puzzle of 50 squarings around [`KEY`]. Finding the primes is the slow
+    /// part, so it is built once for all tests.
+    fn puzzle() -> &'static RswPuzzle {
+        static PUZZLE: OnceLock<RswPuzzle> = OnceLock::new();
+        PUZZLE.get_or_init(|| seal_key(&KEY, 1, 50).unwrap())
+    }
+
+    fn unsolved(err: anyhow::Error) -> (u64, u64) {
+        match VaultError::from(err) {
+            VaultError::PuzzleUnsolved { done, total } => (done, total),
+            other => panic!("expected PuzzleUnsolved, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn solves_and_opens() {
+        let puzzle = puzzle();
+        assert_eq!(puzzle.squarings, 50);
+        let mut progress = puzzle.start();
+        assert!(puzzle.advance(&mut progress, u64::MAX).unwrap());
+        assert_eq!(progress.done, 50);
+        assert_eq!(puzzle.open_key(&progress).unwrap(), KEY);
+
+        // Solved progress stays solved.
+        assert!(puzzle.advance(&mut progress, 10).unwrap());
+        assert_eq!(progress.done, 50);
+    }
+
+    #[test]
+    fn resumes_from_a_checkpoint() {
+        let puzzle = puzzle();
+        let mut progress = puzzle.start();
+        assert!(!puzzle.advance(&mut progress, 20).unwrap());
+        assert!(!puzzle.advance(&mut progress, 20).unwrap());
+        assert_eq!(progress.done, 40);
+
+        let saved = serde_json::to_vec(&progress).unwrap();
+        let mut resumed: PuzzleProgress = serde_json::from_slice(&saved).unwrap();
+        assert!(puzzle.advance(&mut resumed, 20).unwrap());
+        assert_eq!(resumed.done, 50);
+        assert_eq!(puzzle.open_key(&resumed).unwrap(), KEY);
+    }
+
+    #[test]
+    fn refuses_an_unsolved_puzzle() {
+        let puzzle = puzzle();
+        let mut progress = puzzle.start();
+        assert_eq!(unsolved(puzzle.open_key(&progress).unwrap_err()), (0, 50));
+        puzzle.advance(&mut progress, 49).unwrap();
+        assert_eq!(unsolved(puzzle.open_key(&progress).unwrap_err()), (49, 50));
+    }
+
+    #[test]
+    fn refuses_progress_from_another_puzzle() {
+        let puzzle = puzzle();
+        let other = RswPuzzle {
+            squarings: 49,
+            ..puzzle.clone()
+        };
+        let mut progress = other.start();
+        other.advance(&mut progress, u64::MAX).unwrap();
+
+        assert!(puzzle.advance(&mut progress, 1).is_err());
+        assert_eq!(unsolved(puzzle.open_key(&progress).unwrap_err()), (0, 50));
+    }
+
+    #[test]
+    fn detects_a_wrong_solution() {
+        let puzzle = puzzle();
+        let mut progress = puzzle.start();
+        progress.done = puzzle.squarings;
+        let err = VaultError::from(puzzle.open_key(&progress).unwrap_err());
+        assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+    }
+}
@@ -0,0 +1,727 @@
+//! Rotation of the file encryption key (FEK).
+//!
+//! Rotating replaces the FEK with a fresh random key: every metadata record
+//! is re-encrypted with each entry's data key re-wrapped inside it, and
+//! finally the new FEK is wrapped under the password in place of the old.
+//! Blobs are encrypted under per-entry keys and stay as they are, except for
+//! entries written before per-entry keys, whose content is still encrypted
+//! with the FEK: those are re-encrypted to a new blob under a fresh data key.
+//!
+//! Progress is recorded in `rekey_journal.json`, which holds the new FEK
+//! encrypted under the old one. While the journal exists every other
+//! operation refuses to open the vault, so entries are never read under a
+//! mix of keys, and running the rotation again picks up where it stopped:
+//! records that already decrypt under the new FEK are skipped.
+//!
+//! Re-wrapping an entry's data key rewrites its metadata record in one
+//! atomic write and needs no further bookkeeping. An entry that moves to a
+//! new blob is moved in this order:
+//!
+//! 1. the new blob is written and synced to `blobs/<new_id>.tmp`;
+//! 2. the move, including the new metadata record, is appended to
+//!    `rekey_moves.jsonl`;
+//! 3. the blob is renamed into place and the new metadata record written;
+//! 4. the old metadata record and blob are removed.
+//!
+//! Steps 3 and 4 are idempotent and are replayed from the moves log on
+//! resume. Each move is a single appended line, so a rotation costs one
+//! small write per entry moved rather than rewriting its whole history.
+//! Lines carry an HMAC under a key derived from the new FEK, and a move is
+//! only replayed if its blob ids and file name are bare vault names and its
+//! new record decrypts under the new FEK to the new blob, so a forged log
+//! cannot remove or replace files.
+//! Once every entry has moved, the deletion log is re-encrypted in one
+//! atomic write.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Cursor, Read, Write};
+use std::path::Path;
+use zeroize::Zeroize;
+
+use crate::crypto::{decrypt_file_metadata, decrypt_file_metadata_bytes, encrypt_file_metadata, unwrap_data_key, wrap_data_key};
+use crate::error::VaultError;
+use crate::lock::VaultLock;
+use crate::metadata::{
+    blob_path, blobs_dir, deletion_log_path, entry_meta_path, files_meta_dir, is_blob_id, is_entry_meta_file, new_blob_id, read_deletion_log,
+    rekey_journal_path, rekey_moves_path, sync_dir, write_json_atomic, DeletionRecord, EncryptedFileMeta, FileMetaPayload, VaultMetadata,
+};
+use crate::stream::{encrypt_stream, DecryptReader, StreamSummary, DEFAULT_CHUNK_SIZE};
+use crate::vault::{open_vault_key_during_rekey, remove_if_exists, rewrap_fek};
+
+const JOURNAL_KEY_AAD: &[u8] = b"timevault rekey journal v1";
+const KEY_CHECK_LABEL: &[u8] = b"timevault rekey key check";
+/// Label for deriving the moves log MAC key from the new FEK.
+const MOVES_MAC_LABEL: &[u8] = b"timevault rekey moves mac v1";
+
+/// Progress of a key rotation, stored in `rekey_journal.json`.
+#[derive(Serialize, Deserialize)]
+struct RekeyJournal {
+    /// The new FEK encrypted under the old one.
+    new_fek_b64: String,
+    new_fek_nonce_b64: String,
+    /// HMAC of a fixed label under the new FEK, used to tell whether the
+    /// header already holds the new key.
+    new_fek_check: String,
+}
+
+/// An entry whose new blob and metadata record have been prepared.
+#[derive(Serialize, Deserialize)]
+struct MovedEntry {
+    old_blob_id: String,
+    new_blob_id: String,
+    /// File name of the entry's old record under `files_meta/`.
+    old_meta_file: String,
+    /// The entry's metadata record encrypted under the new FEK.
+    new_meta: EncryptedFileMeta,
+}
+
+/// One line of `rekey_moves.jsonl`: a move and its MAC, as written by
+/// [`record_move`].
+#[derive(Deserialize)]
+struct LoggedMove {
+    entry: MovedEntry,
+    mac: String,
+}
+
+impl MovedEntry {
+    fn mac(&self, new_fek: &[u8; 32]) -> Result<Hmac<Sha256>> {
+        let mut kdf = <Hmac<Sha256> as Mac>::new_from_slice(new_fek).map_err(|e| anyhow!(e.to_string()))?;
+        kdf.update(MOVES_MAC_LABEL);
+        let mut mac_key = kdf.finalize().into_bytes();
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key).map_err(|e| anyhow!(e.to_string()))?;
+        mac_key.zeroize();
+        mac.update(&serde_json::to_vec(self)?);
+        Ok(mac)
+    }
+
+    /// Whether this move, read back from the log, is one the rotation could
+    /// have prepared: every name is a bare blob id or `<blob id>.meta.json`,
+    /// and the new record decrypts under `new_fek` to the new blob.
+    fn is_plausible(&self, new_fek: &[u8; 32]) -> bool {
+        is_blob_id(&self.old_blob_id)
+            && is_blob_id(&self.new_blob_id)
+            && self.old_meta_file.strip_suffix(".meta.json").is_some_and(is_blob_id)
+            && decrypt_file_metadata(new_fek, &self.new_meta.encrypted_payload_b64, &self.new_meta.metadata_nonce_b64)
+                .is_ok_and(|payload| payload.blob_id == self.new_blob_id)
+    }
+}
+
+fn key_check(key: &[u8; 32]) -> Result<String> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).map_err(|e| anyhow!(e.to_string()))?;
+    mac.update(KEY_CHECK_LABEL);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+fn new_journal(old_fek: &[u8; 32], new_fek: &[u8; 32]) -> Result<RekeyJournal> {
+    let mut nonce = [0u8; 24];
+    OsRng.fill_bytes(&mut nonce);
+    let sealed = XChaCha20Poly1305::new(Key::from_slice(old_fek))
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: new_fek, aad: JOURNAL_KEY_AAD })
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    Ok(RekeyJournal {
+        new_fek_b64: general_purpose::STANDARD.encode(sealed),
+        new_fek_nonce_b64: general_purpose::STANDARD.encode(nonce),
+        new_fek_check: key_check(new_fek)?,
+    })
+}
+
+fn journal_new_fek(journal: &RekeyJournal, old_fek: &[u8; 32]) -> Result<[u8; 32]> {
+    let nonce = general_purpose::STANDARD.decode(&journal.new_fek_nonce_b64)?;
+    let sealed = general_purpose::STANDARD.decode(&journal.new_fek_b64)?;
+    if nonce.len() != 24 {
+        return Err(anyhow!("Invalid nonce length"));
+    }
+    let mut plain = XChaCha20Poly1305::new(Key::from_slice(old_fek))
+        .decrypt(XNonce::from_slice(&nonce), Payload { msg: &sealed, aad: JOURNAL_KEY_AAD })
+        .map_err(|_| VaultError::tampered("Key rotation journal is corrupt"))?;
+    let key = <[u8; 32]>::try_from(plain.as_slice()).map_err(|_| anyhow!("Invalid key length"));
+    plain.zeroize();
+    key
+}
+
+/// Replaces the vault's FEK with a new random key and re-wraps every entry's
+/// data key under it. Returns the number of entries moved to the new key.
+///
+/// If a previous rotation was interrupted, it is finished with the key it
+/// started with. An entry that cannot be decrypted aborts the rotation and
+/// leaves the journal in place, so no entry is ever lost to a half-finished
+/// rotation.
+pub fn rotate_fek(vault_dir: String, password: String) -> Result<usize> {
+    let vault_path = Path::new(&vault_dir);
+    let _lock = VaultLock::acquire(vault_path)?;
+    let (mut meta, mut fek_arr) = open_vault_key_during_rekey(vault_path, &password)?;
+    let result = rotate_with_key(vault_path, &mut meta, &fek_arr, &password);
+    fek_arr.zeroize();
+    result
+}
+
+fn rotate_with_key(vault_path: &Path, meta: &mut VaultMetadata, old_fek: &[u8; 32], password: &str) -> Result<usize> {
+    let journal_path = rekey_journal_path(vault_path);
+    let moves_path = rekey_moves_path(vault_path);
+    let journal = if journal_path.exists() {
+        let journal: RekeyJournal = serde_json::from_slice(&fs::read(&journal_path)?)?;
+        if journal.new_fek_check == key_check(old_fek)? {
+            // The header was re-wrapped before the journal could be removed.
+            remove_if_exists(&moves_path)?;
+            fs::remove_file(&journal_path)?;
+            return Ok(0);
+        }
+        journal
+    } else {
+        // Moves left by a rotation that finished before removing them.
+        remove_if_exists(&moves_path)?;
+        let mut new_fek = [0u8; 32];
+        OsRng.fill_bytes(&mut new_fek);
+        let journal = new_journal(old_fek, &new_fek);
+        new_fek.zeroize();
+        let journal = journal?;
+        write_json_atomic(&journal_path, &journal)?;
+        journal
+    };
+
+    let mut new_fek = journal_new_fek(&journal, old_fek)?;
+    let result = move_entries(vault_path, old_fek, &new_fek).and_then(|moved| {
+        reencrypt_deletion_log(vault_path, old_fek, &new_fek)?;
+        rewrap_fek(vault_path, meta, &new_fek, password)?;
+        Ok(moved)
+    });
+    new_fek.zeroize();
+    let moved = result?;
+
+    remove_if_exists(&moves_path)?;
+    fs::remove_file(&journal_path)?;
+    Ok(moved)
+}
+
+/// Moves every entry still under the old FEK and returns the number of
+/// entries now under the new one.
+fn move_entries(vault_path: &Path, old_fek: &[u8; 32], new_fek: &[u8; 32]) -> Result<usize> {
+    let moves = read_moves(vault_path, new_fek)?;
+    remove_orphan_blobs(vault_path, &moves)?;
+    for entry in &moves {
+        finish_move(vault_path, entry)?;
+    }
+
+    let fm_dir = files_meta_dir(vault_path);
+    if !fm_dir.exists() {
+        return Ok(0);
+    }
+    let mut meta_files = vec![];
+    for entry in fs::read_dir(&fm_dir)? {
+        let path = entry?.path();
+        if is_entry_meta_file(&path) {
+            meta_files.push(path);
+        }
+    }
+
+    let mut moved = 0;
+    for path in meta_files {
+        let old_meta_file = path.file_name().ok_or_else(|| anyhow!("bad filename"))?.to_string_lossy().to_string();
+        if !old_meta_file.strip_suffix(".meta.json").is_some_and(is_blob_id) {
+            return Err(anyhow!("Entry record {} is misnamed - key rotation stopped", old_meta_file));
+        }
+        let encrypted_meta: EncryptedFileMeta = serde_json::from_slice(&fs::read(&path)?)
+            .map_err(|_| anyhow!("Entry record {} is corrupt - key rotation stopped", old_meta_file))?;
+        moved += 1;
+        if decrypt_file_metadata(new_fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64).is_ok() {
+            continue;
+        }
+        let payload = decrypt_file_metadata(old_fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64)
+            .map_err(|_| anyhow!("Entry record {} is corrupt - key rotation stopped", old_meta_file))?;
+
+        let entry = prepare_move(vault_path, &payload, old_meta_file, old_fek, new_fek)?;
+        if entry.new_blob_id != entry.old_blob_id {
+            record_move(vault_path, new_fek, &entry)?;
+        }
+        finish_move(vault_path, &entry)?;
+    }
+    Ok(moved)
+}
+
+/// Appends `entry` to the moves log, authenticated under `new_fek`, and
+/// syncs it.
+fn record_move(vault_path: &Path, new_fek: &[u8; 32], entry: &MovedEntry) -> Result<()> {
+    let path = rekey_moves_path(vault_path);
+    let created = !path.exists();
+    let mac = hex::encode(entry.mac(new_fek)?.finalize().into_bytes());
+    let mut line = serde_json::to_vec(&serde_json::json!({ "entry": entry, "mac": mac }))?;
+    line.push(b'\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(&line)?;
+    file.sync_data()?;
+    if created {
+        sync_dir(vault_path)?;
+    }
+    Ok(())
+}
+
+/// Reads the moves log. A last line cut short by a crash is dropped from the
+/// file: its blob is still a `.tmp` file, which is discarded, and the entry
+/// is moved again. Any complete line that fails its MAC or does not describe
+/// a plausible move is [`VaultError::Tampered`].
+fn read_moves(vault_path: &Path, new_fek: &[u8; 32]) -> Result<Vec<MovedEntry>> {
+    let path = rekey_moves_path(vault_path);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let raw = fs::read(&path)?;
+    let complete = raw.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+    if complete < raw.len() {
+        let file = OpenOptions::new().write(true).open(&path)?;
+        file.set_len(complete as u64)?;
+        file.sync_data()?;
+    }
+
+    raw[..complete]
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let logged: LoggedMove = serde_json::from_slice(line).map_err(|_| VaultError::tampered("Key rotation journal is corrupt"))?;
+            let mac = hex::decode(&logged.mac).unwrap_or_default();
+            if logged.entry.mac(new_fek)?.verify_slice(&mac).is_err() || !logged.entry.is_plausible(new_fek) {
+                return Err(VaultError::tampered("Key rotation journal is corrupt").into());
+            }
+            Ok(logged.entry)
+        })
+        .collect()
+}
+
+/// Re-encrypts the records of the deletion log that are still under the old
+/// FEK.
+fn reencrypt_deletion_log(vault_path: &Path, old_fek: &[u8; 32], new_fek: &[u8; 32]) -> Result<()> {
+    let mut log = read_deletion_log(vault_path)?;
+    for record in log.iter_mut() {
+        if decrypt_file_metadata_bytes(new_fek, &record.encrypted_payload_b64, &record.metadata_nonce_b64).is_ok() {
+            continue;
+        }
+        let raw = decrypt_file_metadata_bytes(old_fek, &record.encrypted_payload_b64, &record.metadata_nonce_b64)
+            .map_err(|_| anyhow!("Deletion log is corrupt - key rotation stopped"))?;
+        let deletion: DeletionRecord = serde_json::from_slice(&raw)?;
+        let (encrypted_payload_b64, metadata_nonce_b64) = encrypt_file_metadata(new_fek, &deletion)?;
+        record.encrypted_payload_b64 = encrypted_payload_b64;
+        record.metadata_nonce_b64 = metadata_nonce_b64;
+    }
+    if !log.is_empty() {
+        write_json_atomic(&deletion_log_path(vault_path), &log)?;
+    }
+    Ok(())
+}
+
+/// Removes partly written blobs from an interrupted rotation that the
+/// moves log does not know about.
+fn remove_orphan_blobs(vault_path: &Path, moves: &[MovedEntry]) -> Result<()> {
+    let dir = blobs_dir(vault_path);
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        if let Some(blob_id) = name.strip_suffix(".tmp") {
+            if !moves.iter().any(|m| m.new_blob_id == blob_id) {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Re-wraps the entry's data key under the new FEK, or for an entry still
+/// encrypted with the FEK itself writes its content under a fresh data key to
+/// `blobs/<new_id>.tmp`, and encrypts its metadata record under the new FEK.
+fn prepare_move(vault_path: &Path, payload: &FileMetaPayload, old_meta_file: String, old_fek: &[u8; 32], new_fek: &[u8; 32]) -> Result<MovedEntry> {
+    let mut new_payload = payload.clone();
+    if let Some(wrapped) = &payload.data_key {
+        let mut data_key = unwrap_data_key(old_fek, &payload.blob_id, wrapped)?;
+        let rewrapped = wrap_data_key(new_fek, &payload.blob_id, &data_key);
+        data_key.zeroize();
+        new_payload.data_key = Some(rewrapped?);
+    } else if payload.time_lock.is_none() && payload.puzzle.is_none() {
+        new_payload.blob_id = new_blob_id();
+        let tmp = blobs_dir(vault_path).join(format!("{}.tmp", new_payload.blob_id));
+        let mut data_key = [0u8; 32];
+        OsRng.fill_bytes(&mut data_key);
+        let summary = reencrypt_blob(vault_path, payload, old_fek, &data_key, &tmp);
+        let wrapped = wrap_data_key(new_fek, &new_payload.blob_id, &data_key);
+        data_key.zeroize();
+        let summary = match summary {
+            Ok(summary) if summary.size == payload.file_size && summary.sha256 == payload.content_sha256 => summary,
+            Ok(_) => {
+                let _ = fs::remove_file(&tmp);
+                return Err(VaultError::Tampered {
+                    entry: Some(payload.filename.clone()),
+                    reason: "Content hash mismatch".to_string(),
+                }
+                .into());
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                return Err(anyhow!("Could not re-encrypt {}: {}", payload.filename, e));
+            }
+        };
+        new_payload.nonce_b64 = general_purpose::STANDARD.encode(summary.nonce_prefix);
+        new_payload.chunk_size = Some(DEFAULT_CHUNK_SIZE);
+        new_payload.data_key = Some(wrapped?);
+    }
+
+    let (encrypted_payload_b64, metadata_nonce_b64) = encrypt_file_metadata(new_fek, &new_payload)?;
+    Ok(MovedEntry {
+        old_blob_id: payload.blob_id.clone(),
+        new_blob_id: new_payload.blob_id,
+        old_meta_file,
+        new_meta: EncryptedFileMeta {
+            encrypted_payload_b64,
+            metadata_nonce_b64,
+        },
+    })
+}
+
+fn reencrypt_blob(vault_path: &Path, payload: &FileMetaPayload, old_fek: &[u8; 32], new_key: &[u8; 32], dest: &Path) -> Result<StreamSummary> {
+    let nonce_bytes = general_purpose::STANDARD.decode(&payload.nonce_b64)?;
+    let blob = BufReader::new(File::open(blob_path(vault_path, payload))?);
+    let out = File::create(dest)?;
+
+    let summary = match payload.chunk_size {
+        Some(chunk_size) => {
+            let plain = DecryptReader::new(old_fek, &nonce_bytes, chunk_size, blob)?;
+            encrypt_stream(new_key, DEFAULT_CHUNK_SIZE, plain, BufWriter::new(&out))?
+        }
+        None => {
+            if nonce_bytes.len() != 24 {
+                return Err(anyhow!("Invalid nonce length"));
+            }
+            let mut ciphertext = vec![];
+            let mut blob = blob;
+            blob.read_to_end(&mut ciphertext)?;
+            let mut plaintext = XChaCha20Poly1305::new(Key::from_slice(old_fek))
+                .decrypt(XNonce::from_slice(&nonce_bytes), ciphertext.as_ref())
+                .map_err(|_| anyhow!("Decryption failed"))?;
+            let summary = encrypt_stream(new_key, DEFAULT_CHUNK_SIZE, Cursor::new(&plaintext), BufWriter::new(&out));
+            plaintext.zeroize();
+            summary?
+        }
+    };
+    out.sync_all()?;
+    Ok(summary)
+}
+
+/// Puts a prepared move into effect. Safe to repeat.
+fn finish_move(vault_path: &Path, entry: &MovedEntry) -> Result<()> {
+    let blobs = blobs_dir(vault_path);
+    let tmp = blobs.join(format!("{}.tmp", entry.new_blob_id));
+    if tmp.exists() {
+        fs::rename(&tmp, blobs.join(&entry.new_blob_id))?;
+        sync_dir(&blobs)?;
+    }
+    write_json_atomic(&entry_meta_path(vault_path, &entry.new_blob_id), &entry.new_meta)?;
+
+    let old_meta = files_meta_dir(vault_path).join(&entry.old_meta_file);
+    if old_meta != entry_meta_path(vault_path, &entry.new_blob_id) && old_meta.exists() {
+        fs::remove_file(&old_meta)?;
+    }
+    if entry.old_blob_id != entry.new_blob_id {
+        let old_blob = blobs.join(&entry.old_blob_id);
+        if old_blob.exists() {
+            fs::remove_file(&old_blob)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{BoxFuture, TimePolicy, TimeSample, TimeSource, TimeVerifier};
+    use crate::vault::{add_file, init_vault, unlock_vault_with_verifier, verify_password};
+    use std::path::PathBuf;
+
+    const PASSWORD: &str = "correct horse battery staple";
+
+    struct Clock;
+
+    impl TimeSource for Clock {
+        fn name(&self) -> String {
+            "clock".to_string()
+        }
+
+        fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>> {
+            let sample = TimeSample {
+                unix_secs: 2_000_000_000,
+                source: self.name(),
+                authenticated: true,
+            };
+            Box::pin(async move { Ok(sample) })
+        }
+    }
+
+    struct Fixture {
+        root: tempfile::TempDir,
+        vault: PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let root = tempfile::tempdir().unwrap();
+            let vault = root.path().join("vault");
+            init_vault(vault.to_string_lossy().into_owned(), PASSWORD.to_string()).unwrap();
+            Self { root, vault }
+        }
+
+        fn vault_dir(&self) -> String {
+            self.vault.to_string_lossy().into_owned()
+        }
+
+        fn add(&self, name: &str, contents: &[u8]) {
+            let path = self.root.path().join(name);
+            fs::write(&path, contents).unwrap();
+            add_file(self.vault_dir(), path.to_string_lossy().into_owned(), PASSWORD.to_string(), 1).unwrap();
+        }
+
+        /// Adds an entry in the format used before per-entry keys, with its
+        /// content encrypted directly under the FEK.
+        fn add_legacy(&self, fek: &[u8; 32], name: &str, contents: &[u8]) {
+            let blob_id = new_blob_id();
+            let summary = encrypt_stream(fek, DEFAULT_CHUNK_SIZE, contents, File::create(blobs_dir(&self.vault).join(&blob_id)).unwrap()).unwrap();
+            let payload = FileMetaPayload {
+                filename: name.to_string(),
+                file_size: summary.size,
+                file_unlock_date: 1,
+                content_sha256: summary.sha256,
+                blob_id,
+                nonce_b64: general_purpose::STANDARD.encode(summary.nonce_prefix),
+                chunk_size: Some(DEFAULT_CHUNK_SIZE),
+                data_key: None,
+                time_lock: None,
+                puzzle: None,
+            };
+            let (encrypted_payload_b64, metadata_nonce_b64) = encrypt_file_metadata(fek, &payload).unwrap();
+            let record = EncryptedFileMeta {
+                encrypted_payload_b64,
+                metadata_nonce_b64,
+            };
+            write_json_atomic(&entry_meta_path(&self.vault, &payload.blob_id), &record).unwrap();
+        }
+
+        fn fek(&self) -> [u8; 32] {
+            open_vault_key_during_rekey(&self.vault, PASSWORD).unwrap().1
+        }
+
+        /// Starts a rotation the way [`rotate_with_key`] does and returns the
+        /// new key, as if the process stopped right after writing the journal.
+        fn start_rotation(&self, old_fek: &[u8; 32]) -> [u8; 32] {
+            let mut new_fek = [0u8; 32];
+            OsRng.fill_bytes(&mut new_fek);
+            write_json_atomic(&rekey_journal_path(&self.vault), &new_journal(old_fek, &new_fek).unwrap()).unwrap();
+            new_fek
+        }
+
+        fn records(&self, fek: &[u8; 32]) -> Vec<FileMetaPayload> {
+            let mut records = vec![];
+            for entry in fs::read_dir(files_meta_dir(&self.vault)).unwrap() {
+                let path = entry.unwrap().path();
+                if is_entry_meta_file(&path) {
+                    let encrypted: EncryptedFileMeta = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+                    records.push(decrypt_file_metadata(fek, &encrypted.encrypted_payload_b64, &encrypted.metadata_nonce_b64).unwrap());
+                }
+            }
+            records
+        }
+
+        fn blob_names(&self) -> Vec<String> {
+            let mut names: Vec<String> = fs::read_dir(blobs_dir(&self.vault))
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        }
+
+        async fn unlock_all(&self) -> Vec<(String, Vec<u8>)> {
+            let out = self.root.path().join("out");
+            let verifier = TimeVerifier::new(
+                vec![Box::new(Clock)],
+                TimePolicy {
+                    min_agreeing: 1,
+                    ..TimePolicy::default()
+                },
+            );
+            unlock_vault_with_verifier(self.vault_dir(), out.to_string_lossy().into_owned(), PASSWORD.to_string(), &verifier)
+                .await
+                .unwrap();
+            let mut files: Vec<(String, Vec<u8>)> = fs::read_dir(&out)
+                .unwrap()
+                .map(|entry| {
+                    let path = entry.unwrap().path();
+                    (path.file_name().unwrap().to_string_lossy().into_owned(), fs::read(&path).unwrap())
+                })
+                .collect();
+            files.sort();
+            files
+        }
+    }
+
+    fn sample_files() -> Vec<(String, Vec<u8>)> {
+        vec![
+            ("a.txt".to_string(), b"first".to_vec()),
+            ("b.txt".to_string(), vec![7; 100_000]),
+            ("c.txt".to_string(), b"third".to_vec()),
+        ]
+    }
+
+    #[tokio::test]
+    async fn resumes_an_interrupted_rotation() {
+        let vault = Fixture::new();
+        for (name, contents) in sample_files() {
+            vault.add(&name, &contents);
+        }
+        let old_fek = vault.fek();
+        let new_fek = vault.start_rotation(&old_fek);
+
+        // Move one entry before the "crash".
+        let first = &vault.records(&old_fek)[0];
+        let entry = prepare_move(&vault.vault, first, format!("{}.meta.json", first.blob_id), &old_fek, &new_fek).unwrap();
+        finish_move(&vault.vault, &entry).unwrap();
+
+        let err = VaultError::from(verify_password(vault.vault_dir(), PASSWORD.to_string()).unwrap_err());
+        assert!(matches!(err, VaultError::RekeyPending), "{:?}", err);
+
+        assert_eq!(rotate_fek(vault.vault_dir(), PASSWORD.to_string()).unwrap(), 3);
+        assert!(!rekey_journal_path(&vault.vault).exists());
+        assert_eq!(vault.fek(), new_fek);
+        assert_eq!(vault.records(&new_fek).len(), 3);
+        assert_eq!(vault.unlock_all().await, sample_files());
+    }
+
+    #[tokio::test]
+    async fn resumes_a_move_to_a_new_blob() {
+        let vault = Fixture::new();
+        let old_fek = vault.fek();
+        let files = sample_files();
+        vault.add(&files[0].0, &files[0].1);
+        vault.add_legacy(&old_fek, &files[1].0, &files[1].1);
+        vault.add_legacy(&old_fek, &files[2].0, &files[2].1);
+        let new_fek = vault.start_rotation(&old_fek);
+
+        // Prepare and record one legacy move but stop before putting it into
+        // effect, then start appending another record and stop halfway.
+        let legacy = vault.records(&old_fek).into_iter().find(|record| record.data_key.is_none()).unwrap();
+        let entry = prepare_move(&vault.vault, &legacy, format!("{}.meta.json", legacy.blob_id), &old_fek, &new_fek).unwrap();
+        record_move(&vault.vault, &new_fek, &entry).unwrap();
+        let mut log = OpenOptions::new().append(true).open(rekey_moves_path(&vault.vault)).unwrap();
+        log.write_all(br#"{"old_blob_id":"#).unwrap();
+        drop(log);
+        File::create(blobs_dir(&vault.vault).join(format!("{}.tmp", new_blob_id()))).unwrap();
+
+        assert_eq!(rotate_fek(vault.vault_dir(), PASSWORD.to_string()).unwrap(), 3);
+        assert!(!rekey_journal_path(&vault.vault).exists());
+        assert!(!rekey_moves_path(&vault.vault).exists());
+
+        let records = vault.records(&new_fek);
+        assert!(records.iter().all(|record| record.data_key.is_some()));
+        let mut blob_ids: Vec<String> = records.into_iter().map(|record| record.blob_id).collect();
+        blob_ids.sort();
+        assert_eq!(vault.blob_names(), blob_ids);
+        assert!(blob_ids.contains(&entry.new_blob_id) && !blob_ids.contains(&legacy.blob_id));
+        assert_eq!(vault.unlock_all().await, files);
+    }
+
+    /// Prepares the move of every legacy entry, as a rotation to `new_fek`
+    /// would, without putting any of them into effect.
+    fn legacy_moves(vault: &Fixture, old_fek: &[u8; 32], new_fek: &[u8; 32]) -> Vec<MovedEntry> {
+        vault
+            .records(old_fek)
+            .iter()
+            .filter(|record| record.data_key.is_none())
+            .map(|record| prepare_move(&vault.vault, record, format!("{}.meta.json", record.blob_id), old_fek, new_fek).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn moves_are_appended_one_line_each() {
+        let vault = Fixture::new();
+        let old_fek = vault.fek();
+        for (name, contents) in sample_files() {
+            vault.add_legacy(&old_fek, &name, &contents);
+        }
+        let new_fek = vault.start_rotation(&old_fek);
+        let entries = legacy_moves(&vault, &old_fek, &new_fek);
+        let new_ids: Vec<&str> = entries.iter().map(|entry| entry.new_blob_id.as_str()).collect();
+
+        record_move(&vault.vault, &new_fek, &entries[0]).unwrap();
+        let one = fs::metadata(rekey_moves_path(&vault.vault)).unwrap().len();
+        record_move(&vault.vault, &new_fek, &entries[1]).unwrap();
+        assert!(fs::metadata(rekey_moves_path(&vault.vault)).unwrap().len() > one);
+        let two = fs::metadata(rekey_moves_path(&vault.vault)).unwrap().len();
+
+        let moves = read_moves(&vault.vault, &new_fek).unwrap();
+        assert_eq!(moves.iter().map(|m| m.new_blob_id.as_str()).collect::<Vec<_>>(), new_ids[..2]);
+
+        // A record cut short is dropped, so the next one starts on its own line.
+        let mut log = OpenOptions::new().append(true).open(rekey_moves_path(&vault.vault)).unwrap();
+        log.write_all(br#"{"entry":{"old_blob_id":"#).unwrap();
+        drop(log);
+        assert_eq!(read_moves(&vault.vault, &new_fek).unwrap().len(), 2);
+        assert_eq!(fs::metadata(rekey_moves_path(&vault.vault)).unwrap().len(), two);
+        record_move(&vault.vault, &new_fek, &entries[2]).unwrap();
+        assert_eq!(read_moves(&vault.vault, &new_fek).unwrap().len(), 3);
+
+        fs::write(rekey_moves_path(&vault.vault), b"not json\n").unwrap();
+        let err = VaultError::from(read_moves(&vault.vault, &new_fek).map(|_| ()).unwrap_err());
+        assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn forged_moves_are_refused() {
+        let vault = Fixture::new();
+        let old_fek = vault.fek();
+        vault.add_legacy(&old_fek, "a.txt", b"first");
+        vault.add_legacy(&old_fek, "b.txt", b"second");
+        let new_fek = vault.start_rotation(&old_fek);
+        let outside = vault.root.path().join("outside.txt");
+        fs::write(&outside, b"not part of the vault").unwrap();
+
+        let forgeries: [fn(&mut MovedEntry, &MovedEntry); 4] = [
+            |entry, _| entry.old_blob_id = "../../outside.txt".to_string(),
+            |entry, _| entry.new_blob_id = "../outside".to_string(),
+            |entry, _| entry.old_meta_file = "../../outside.txt".to_string(),
+            // A genuine record of another entry, written under this one's id.
+            |entry, other| {
+                entry.new_meta = EncryptedFileMeta {
+                    encrypted_payload_b64: other.new_meta.encrypted_payload_b64.clone(),
+                    metadata_nonce_b64: other.new_meta.metadata_nonce_b64.clone(),
+                }
+            },
+        ];
+        for forge in forgeries {
+            let mut entries = legacy_moves(&vault, &old_fek, &new_fek);
+            let other = entries.pop().unwrap();
+            let mut entry = entries.pop().unwrap();
+            forge(&mut entry, &other);
+
+            // Refused even with a valid MAC, which needs the new FEK.
+            remove_if_exists(&rekey_moves_path(&vault.vault)).unwrap();
+            record_move(&vault.vault, &new_fek, &entry).unwrap();
+            let err = VaultError::from(rotate_fek(vault.vault_dir(), PASSWORD.to_string()).unwrap_err());
+            assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+        }
+
+        // A genuine move is refused without a MAC under the new FEK.
+        remove_if_exists(&rekey_moves_path(&vault.vault)).unwrap();
+        record_move(&vault.vault, &[0x5a; 32], &legacy_moves(&vault, &old_fek, &new_fek)[0]).unwrap();
+        let err = VaultError::from(rotate_fek(vault.vault_dir(), PASSWORD.to_string()).unwrap_err());
+        assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+
+        assert!(outside.exists());
+        assert_eq!(vault.records(&old_fek).len(), 2);
+    }
+}
@@ -0,0 +1,103 @@
+//! Unlocked vault sessions.
+//!
+//! Opening a vault runs the Argon2 derivation once and keeps the unwrapped
+//! FEK in a [`VaultSession`], so a long-lived caller such as the desktop app
+//! can run many operations without holding on to the password. The key lives
+//! in its own heap allocation, locked into RAM where the platform allows it
+//! so it is never written to swap, and is zeroized when the session is
+//! dropped.
+//!
+//! A session does not hold the vault lock between operations. Each operation
+//! locks the vault and re-checks its header with the session key, so changes
+//! made by other processes in the meantime are seen; a password change keeps
+//! the session usable, while a key rotation ends it with
+//! [`VaultError::SessionExpired`].
+
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroize;
+
+use crate::crypto::verify_vault_state;
+use crate::error::VaultError;
+use crate::lock::VaultLock;
+use crate::metadata::{read_vault_metadata, rekey_journal_path, VaultMetadata};
+use crate::vault::{open_vault_key, recover_pending_op};
+
+/// A 32-byte key kept out of swap and zeroized on drop.
+struct SecretKey {
+    // Declared first so the pages are unlocked before the box is freed.
+    _guard: Option<region::LockGuard>,
+    bytes: Box<[u8; 32]>,
+}
+
+impl SecretKey {
+    fn new(key: &[u8; 32]) -> Self {
+        let mut bytes = Box::new([0u8; 32]);
+        // Locking can fail under a low RLIMIT_MEMLOCK; the key is still
+        // zeroized on drop, so carry on without it.
+        let guard = region::lock(bytes.as_ptr(), bytes.len()).ok();
+        bytes.copy_from_slice(key);
+        Self { _guard: guard, bytes }
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+/// An open vault, holding its unwrapped FEK. See the [module docs](self).
+pub struct VaultSession {
+    vault_path: PathBuf,
+    fek: SecretKey,
+    /// The wrapped FEK at the time the session was opened, to tell a key
+    /// rotation by someone else from tampering.
+    wrapped_fek_b64: String,
+}
+
+/// Unwraps the FEK of the vault at `vault_dir` with `password` and returns a
+/// session holding it.
+///
+/// Opening does everything an operation with the password does first:
+/// older vaults are upgraded, the header's state MAC is verified and any add
+/// or delete that was cut short is recovered.
+pub fn open_vault(vault_dir: String, password: String) -> Result<VaultSession> {
+    let vault_path = PathBuf::from(vault_dir);
+    let _lock = VaultLock::acquire(&vault_path)?;
+    let (meta, mut fek_arr) = open_vault_key(&vault_path, &password)?;
+    let fek = SecretKey::new(&fek_arr);
+    fek_arr.zeroize();
+    Ok(VaultSession {
+        vault_path,
+        fek,
+        wrapped_fek_b64: meta.wrapped_fek_b64,
+    })
+}
+
+impl VaultSession {
+    pub fn vault_dir(&self) -> &Path {
+        &self.vault_path
+    }
+
+    pub(crate) fn fek(&self) -> &[u8; 32] {
+        &self.fek.bytes
+    }
+
+    /// Locks the vault for one operation and reads and verifies its header.
+    pub(crate) fn begin(&self) -> Result<(VaultLock, VaultMetadata)> {
+        let lock = VaultLock::acquire(&self.vault_path)?;
+        if rekey_journal_path(&self.vault_path).exists() {
+            return Err(VaultError::RekeyPending.into());
+        }
+        let meta = read_vault_metadata(&self.vault_path)?;
+        if let Err(e) = verify_vault_state(self.fek(), &meta) {
+            if meta.wrapped_fek_b64 != self.wrapped_fek_b64 {
+                return Err(VaultError::SessionExpired.into());
+            }
+            return Err(e);
+        }
+        recover_pending_op(&self.vault_path, self.fek(), &meta)?;
+        Ok((lock, meta))
+    }
+}
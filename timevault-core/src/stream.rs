@@ -0,0 +1,277 @@
+//! Chunked STREAM encryption of entry content.
+//!
+//! Content is split into fixed-size chunks, each sealed with
+//! XChaCha20-Poly1305 under a nonce built from a random 19-byte prefix, a
+//! 32-bit big-endian chunk counter and a last-chunk flag (the STREAM
+//! construction of Hoang, Reyhanitabar, Rogaway and Vizár). Reordering,
+//! dropping, truncating or appending chunks makes decryption fail, and memory
+//! use is bounded by the chunk size regardless of the file size.
+
+use anyhow::{anyhow, Result};
+use chacha20poly1305::aead::generic_array::GenericArray;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::KeyInit;
+use chacha20poly1305::{Key, XChaCha20Poly1305};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+use zeroize::Zeroize;
+
+use crate::error::VaultError;
+
+/// Plaintext bytes per chunk for newly added entries.
+pub const DEFAULT_CHUNK_SIZE: u32 = 64 * 1024;
+
+/// Length of the per-entry nonce prefix.
+pub const NONCE_PREFIX_LEN: usize = 19;
+
+const TAG_LEN: usize = 16;
+
+/// Size and hash of the plaintext that passed through a stream.
+pub(crate) struct StreamSummary {
+    pub nonce_prefix: [u8; NONCE_PREFIX_LEN],
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Encrypts everything from `reader` into `writer` under a fresh nonce prefix.
+pub(crate) fn encrypt_stream<R: Read, W: Write>(fek: &[u8; 32], chunk_size: u32, mut reader: R, mut writer: W) -> Result<StreamSummary> {
+    let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+    OsRng.fill_bytes(&mut nonce_prefix);
+
+    let aead = XChaCha20Poly1305::new(Key::from_slice(fek));
+    let mut encryptor = EncryptorBE32::from_aead(aead, GenericArray::from_slice(&nonce_prefix));
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+
+    let chunk_size = chunk_size as usize;
+    let mut current = vec![0u8; chunk_size];
+    let mut next = vec![0u8; chunk_size];
+    let mut current_len = read_full(&mut reader, &mut current)?;
+
+    loop {
+        hasher.update(&current[..current_len]);
+        size += current_len as u64;
+
+        let next_len = if current_len == chunk_size { read_full(&mut reader, &mut next)? } else { 0 };
+        if next_len == 0 {
+            break;
+        }
+
+        let sealed = encryptor
+            .encrypt_next(&current[..current_len])
+            .map_err(|_| anyhow!("Encryption failed"))?;
+        writer.write_all(&sealed)?;
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+    }
+
+    let sealed = encryptor
+        .encrypt_last(&current[..current_len])
+        .map_err(|_| anyhow!("Encryption failed"))?;
+    writer.write_all(&sealed)?;
+    writer.flush()?;
+    current.zeroize();
+    next.zeroize();
+
+    Ok(StreamSummary {
+        nonce_prefix,
+        size,
+        sha256: hex::encode(hasher.finalize()),
+    })
+}
+
+/// Decrypts a stream written by [`encrypt_stream`] from `reader` into
+/// `writer`, returning the size and hash of the recovered plaintext.
+///
+/// Fails with [`VaultError::Tampered`] if any chunk does not authenticate or
+/// the stream was truncated or extended. Plaintext from chunks before the
+/// failure may already have been written, so callers should write to a
+/// temporary location.
+pub(crate) fn decrypt_stream<R: Read, W: Write>(fek: &[u8; 32], nonce_prefix: &[u8], chunk_size: u32, reader: R, mut writer: W) -> Result<(u64, String)> {
+    let mut plain = DecryptReader::new(fek, nonce_prefix, chunk_size, reader)?;
+    io::copy(&mut plain, &mut writer).map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => anyhow::Error::from(VaultError::tampered("Decryption failed")),
+        _ => e.into(),
+    })?;
+    writer.flush()?;
+    Ok(plain.finish())
+}
+
+/// A reader yielding the plaintext of a stream written by [`encrypt_stream`],
+/// decrypting one chunk at a time.
+///
+/// A chunk that fails to authenticate, or a truncated or extended stream,
+/// surfaces as an [`io::ErrorKind::InvalidData`] error.
+pub(crate) struct DecryptReader<R: Read> {
+    reader: R,
+    decryptor: Option<DecryptorBE32<XChaCha20Poly1305>>,
+    sealed_size: usize,
+    current: Vec<u8>,
+    current_len: usize,
+    next: Vec<u8>,
+    plain: Vec<u8>,
+    pos: usize,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<R: Read> DecryptReader<R> {
+    pub(crate) fn new(fek: &[u8; 32], nonce_prefix: &[u8], chunk_size: u32, mut reader: R) -> Result<Self> {
+        if nonce_prefix.len() != NONCE_PREFIX_LEN {
+            return Err(anyhow!("Invalid stream nonce length"));
+        }
+        if chunk_size == 0 {
+            return Err(anyhow!("Invalid stream chunk size"));
+        }
+
+        let aead = XChaCha20Poly1305::new(Key::from_slice(fek));
+        let sealed_size = chunk_size as usize + TAG_LEN;
+        let mut current = vec![0u8; sealed_size];
+        let current_len = read_full(&mut reader, &mut current)?;
+
+        Ok(Self {
+            reader,
+            decryptor: Some(DecryptorBE32::from_aead(aead, GenericArray::from_slice(nonce_prefix))),
+            sealed_size,
+            current,
+            current_len,
+            next: vec![0u8; sealed_size],
+            plain: vec![],
+            pos: 0,
+            hasher: Sha256::new(),
+            size: 0,
+        })
+    }
+
+    /// Size and hex SHA-256 of the plaintext read so far.
+    pub(crate) fn finish(mut self) -> (u64, String) {
+        (self.size, hex::encode(std::mem::take(&mut self.hasher).finalize()))
+    }
+
+    /// Decrypts the next chunk into `self.plain`. Returns `false` once the
+    /// last chunk has been consumed.
+    fn fill(&mut self) -> io::Result<bool> {
+        if self.decryptor.is_none() {
+            return Ok(false);
+        }
+
+        let next_len = if self.current_len == self.sealed_size { read_full(&mut self.reader, &mut self.next)? } else { 0 };
+        self.plain.zeroize();
+        let sealed = &self.current[..self.current_len];
+        let plain = match (next_len, self.decryptor.take()) {
+            (0, Some(decryptor)) => decryptor.decrypt_last(sealed),
+            (_, Some(mut decryptor)) => {
+                let plain = decryptor.decrypt_next(sealed);
+                self.decryptor = Some(decryptor);
+                plain
+            }
+            (_, None) => return Ok(false),
+        };
+        self.plain = plain.map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Decryption failed"))?;
+        self.pos = 0;
+        self.hasher.update(&self.plain);
+        self.size += self.plain.len() as u64;
+
+        std::mem::swap(&mut self.current, &mut self.next);
+        self.current_len = next_len;
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for DecryptReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.plain.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let n = buf.len().min(self.plain.len() - self.pos);
+        buf[..n].copy_from_slice(&self.plain[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<R: Read> Drop for DecryptReader<R> {
+    fn drop(&mut self) {
+        self.plain.zeroize();
+    }
+}
+
+/// Reads until `buf` is full or the reader is exhausted.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+    const CHUNK: u32 = 16;
+
+    fn encrypt(plain: &[u8]) -> (StreamSummary, Vec<u8>) {
+        let mut sealed = vec![];
+        let summary = encrypt_stream(&KEY, CHUNK, plain, &mut sealed).unwrap();
+        (summary, sealed)
+    }
+
+    fn decrypt(summary: &StreamSummary, sealed: &[u8]) -> Result<(Vec<u8>, u64, String)> {
+        let mut plain = vec![];
+        let (size, sha256) = decrypt_stream(&KEY, &summary.nonce_prefix, CHUNK, sealed, &mut plain)?;
+        Ok((plain, size, sha256))
+    }
+
+    #[test]
+    fn round_trips_at_chunk_boundaries() {
+        for len in [0usize, 1, 15, 16, 17, 32, 100] {
+            let plain: Vec<u8> = (0..len as u8).collect();
+            let (summary, sealed) = encrypt(&plain);
+            let chunks = len.div_ceil(CHUNK as usize).max(1);
+            assert_eq!(sealed.len(), len + chunks * TAG_LEN);
+            let (decrypted, size, sha256) = decrypt(&summary, &sealed).unwrap();
+            assert_eq!(decrypted, plain);
+            assert_eq!((size, sha256), (summary.size, summary.sha256.clone()));
+            assert_eq!(summary.sha256, hex::encode(Sha256::digest(&plain)));
+        }
+    }
+
+    #[test]
+    fn rejects_modified_streams() {
+        let plain = [42u8; 40];
+        let (summary, sealed) = encrypt(&plain);
+        let sealed_chunk = CHUNK as usize + TAG_LEN;
+
+        let mut flipped = sealed.clone();
+        flipped[sealed_chunk + 3] ^= 1;
+        let truncated = &sealed[..2 * sealed_chunk];
+        let mut extended = sealed.clone();
+        extended.extend_from_slice(&sealed[..sealed_chunk]);
+        let mut reordered = sealed.clone();
+        reordered.copy_within(0..sealed_chunk, sealed_chunk);
+        reordered[..sealed_chunk].copy_from_slice(&sealed[sealed_chunk..2 * sealed_chunk]);
+
+        for (case, stream) in [("flipped", &flipped[..]), ("truncated", truncated), ("extended", &extended[..]), ("reordered", &reordered[..])] {
+            let err = VaultError::from(decrypt(&summary, stream).unwrap_err());
+            assert!(matches!(err, VaultError::Tampered { .. }), "{}: {:?}", case, err);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_key() {
+        let (summary, sealed) = encrypt(b"secret");
+        let mut plain = vec![];
+        assert!(decrypt_stream(&[8; 32], &summary.nonce_prefix, CHUNK, &sealed[..], &mut plain).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Time lookup over HTTP: public JSON time APIs and HTTPS `Date` headers.
+//!
+//! Neither is signed. Over `https://` the response is authenticated by TLS;
+//! over plain `http://` anyone able to tamper with the connection can forge
+//! it, so such sources are marked unauthenticated and cannot satisfy a
+//! [`TimePolicy`](super::TimePolicy) on their own.
+
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use reqwest::Client;
+use std::time::Duration as StdDuration;
+use tokio::time::sleep;
+
+use super::{BoxFuture, TimeSample, TimeSource};
+
+const USER_AGENT: &str = "vault-client/1.0";
+
+/// How a JSON time API reports the current time.
+#[derive(Clone, Copy, Debug)]
+pub enum JsonTimeFormat {
+    /// `{"unixtime": <seconds>}`, as served by worldtimeapi.org.
+    UnixTime,
+    /// `{"dateTime": "<RFC 3339>"}`, as served by timeapi.io.
+    DateTime,
+    /// `{"currentDateTime": "<RFC 3339>"}` or `{"currentFileTime": <100ns
+    /// ticks since 1601>}`, as served by worldclockapi.com.
+    WorldClock,
+}
+
+/// A public JSON time API.
+#[derive(Clone, Debug)]
+pub struct JsonTimeApi {
+    pub label: String,
+    pub url: String,
+    pub format: JsonTimeFormat,
+}
+
+impl JsonTimeApi {
+    pub fn new(label: &str, url: &str, format: JsonTimeFormat) -> Self {
+        Self {
+            label: label.to_string(),
+            url: url.to_string(),
+            format,
+        }
+    }
+
+    /// The endpoints TimeVault has historically queried.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("[Server 1]", "https://worldtimeapi.org/api/timezone/Etc/UTC", JsonTimeFormat::UnixTime),
+            Self::new("[Server 2]", "http://worldclockapi.com/api/json/utc/now", JsonTimeFormat::WorldClock),
+            Self::new("[Server 3]", "https://timeapi.io/api/Time/current/zone?timeZone=UTC", JsonTimeFormat::DateTime),
+            Self::new("[Server 4]", "https://worldtimeapi.org/api/ip", JsonTimeFormat::UnixTime),
+        ]
+    }
+
+    /// Extracts the Unix time from a response body in this API's format.
+    pub fn parse(&self, json: &serde_json::Value) -> Option<u64> {
+        match self.format {
+            JsonTimeFormat::UnixTime => json["unixtime"].as_i64().and_then(|t| u64::try_from(t).ok()),
+            JsonTimeFormat::DateTime => parse_rfc3339(json["dateTime"].as_str()?),
+            JsonTimeFormat::WorldClock => {
+                if let Some(secs) = json["currentDateTime"].as_str().and_then(parse_rfc3339) {
+                    return Some(secs);
+                }
+                let filetime = json["currentFileTime"].as_i64()?;
+                u64::try_from((filetime / 10_000_000) - 11_644_473_600).ok()
+            }
+        }
+    }
+
+    async fn fetch_once(&self, client: &Client) -> Result<u64> {
+        let resp = client.get(&self.url).send().await?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("{} returned {}", self.label, resp.status()));
+        }
+        let json = resp.json::<serde_json::Value>().await?;
+        self.parse(&json).ok_or_else(|| anyhow!("{} returned an unrecognised response", self.label))
+    }
+}
+
+impl TimeSource for JsonTimeApi {
+    fn name(&self) -> String {
+        self.label.clone()
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>> {
+        Box::pin(async move {
+            let client = Client::builder().user_agent(USER_AGENT).build()?;
+            Ok(TimeSample {
+                unix_secs: self.fetch_once(&client).await?,
+                source: self.label.clone(),
+                authenticated: self.url.starts_with("https://"),
+            })
+        })
+    }
+}
+
+/// Reads the `Date` header of an HTTPS response. Any well-run web server
+/// keeps its clock in sync, and TLS authenticates the header.
+#[derive(Clone, Debug)]
+pub struct HttpDateSource {
+    pub url: String,
+}
+
+impl HttpDateSource {
+    pub fn new(url: &str) -> Self {
+        Self { url: url.to_string() }
+    }
+
+    pub fn defaults() -> Vec<Self> {
+        vec![Self::new("https://www.cloudflare.com"), Self::new("https://www.google.com")]
+    }
+}
+
+impl TimeSource for HttpDateSource {
+    fn name(&self) -> String {
+        format!("[HTTPS Date: {}]", self.url.trim_start_matches("https://").trim_start_matches("http://"))
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>> {
+        Box::pin(async move {
+            let client = Client::builder().user_agent(USER_AGENT).build()?;
+            let resp = client.head(&self.url).send().await?;
+            let date = resp
+                .headers()
+                .get(reqwest::header::DATE)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| anyhow!("{} did not send a Date header", self.url))?;
+            let parsed = DateTime::parse_from_rfc2822(date)?;
+            Ok(TimeSample {
+                unix_secs: u64::try_from(parsed.timestamp())?,
+                source: self.name(),
+                authenticated: self.url.starts_with("https://"),
+            })
+        })
+    }
+}
+
+fn parse_rfc3339(s: &str) -> Option<u64> {
+    DateTime::parse_from_rfc3339(s).ok().and_then(|dt| u64::try_from(dt.timestamp()).ok())
+}
+
+/// Queries the public JSON time endpoints in order, retrying each with
+/// backoff, and returns the first successfully parsed Unix time with its
+/// server label.
+///
+/// The first answer wins, so this offers no protection against a lying or
+/// spoofed server; prefer [`fetch_verified_unixtime`](super::fetch_verified_unixtime).
+pub async fn fetch_public_unixtime_with_retries() -> Result<(u64, String)> {
+    let client = Client::builder()
+        .user_agent(USER_AGENT)
+        .build()?;
+
+    for api in JsonTimeApi::defaults() {
+        let mut attempt = 0u32;
+        let max_attempts = 3u32;
+        loop {
+            attempt += 1;
+            if let Ok(unix_time) = api.fetch_once(&client).await {
+                return Ok((unix_time, api.label.clone()));
+            }
+            if attempt >= max_attempts {
+                break;
+            }
+            let backoff = 500u64 * (1u64 << (attempt - 1));
+            sleep(StdDuration::from_millis(backoff)).await;
+        }
+    }
+
+    Err(anyhow!("Date and time vertification failed. "))
+}
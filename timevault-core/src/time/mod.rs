@@ -0,0 +1,263 @@
+//! Public time verification.
+//!
+//! Each way of learning the current time implements [`TimeSource`]. A
+//! [`TimeVerifier`] queries several sources concurrently and only accepts a
+//! time that enough of them agree on, as configured by its [`TimePolicy`], so
+//! a single lying or spoofed server cannot unlock files early. Vault
+//! operations use [`TimeVerifier::default`] unless given another verifier.
+
+mod http;
+pub mod ntp;
+pub mod roughtime;
+
+use anyhow::Result;
+use futures::future::join_all;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::time::timeout;
+
+use crate::error::VaultError;
+
+pub use http::{fetch_public_unixtime_with_retries, HttpDateSource, JsonTimeApi, JsonTimeFormat};
+pub use ntp::SntpSource;
+pub use roughtime::{RoughtimeClient, RoughtimeServer};
+
+/// Boxed future returned by [`TimeSource::fetch`].
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// One reading of the current time.
+#[derive(Clone, Debug)]
+pub struct TimeSample {
+    pub unix_secs: u64,
+    /// Label of the source that produced the reading.
+    pub source: String,
+    /// Whether the reading is cryptographically authenticated (a signed
+    /// Roughtime reply or a response received over TLS).
+    pub authenticated: bool,
+}
+
+/// A way of learning the current time.
+pub trait TimeSource: Send + Sync {
+    /// Label used in logs and error messages.
+    fn name(&self) -> String;
+
+    /// Makes a single attempt to read the current time.
+    fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>>;
+}
+
+/// When a set of time samples is trusted.
+#[derive(Clone, Debug)]
+pub struct TimePolicy {
+    /// Minimum number of sources whose readings must lie within
+    /// `max_skew_secs` of each other.
+    pub min_agreeing: usize,
+    /// Minimum number of authenticated sources among those agreeing.
+    pub min_authenticated: usize,
+    /// Largest spread, in seconds, between agreeing readings.
+    pub max_skew_secs: u64,
+    /// How long to wait for each source.
+    pub timeout: Duration,
+}
+
+impl Default for TimePolicy {
+    fn default() -> Self {
+        Self {
+            min_agreeing: 2,
+            min_authenticated: 1,
+            max_skew_secs: 60,
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl TimePolicy {
+    /// Picks the largest group of samples spanning at most `max_skew_secs`
+    /// that satisfies the policy, preferring the earliest group on ties, and
+    /// returns its median. Fails if no group satisfies the policy.
+    pub fn decide(&self, samples: &[TimeSample]) -> Result<(u64, Vec<String>)> {
+        let mut sorted: Vec<&TimeSample> = samples.iter().collect();
+        sorted.sort_by_key(|s| s.unix_secs);
+
+        let mut best: Option<&[&TimeSample]> = None;
+        let mut end = 0;
+        for start in 0..sorted.len() {
+            end = end.max(start);
+            while end < sorted.len() && sorted[end].unix_secs - sorted[start].unix_secs <= self.max_skew_secs {
+                end += 1;
+            }
+            let group = &sorted[start..end];
+            let authenticated = group.iter().filter(|s| s.authenticated).count();
+            if group.len() >= self.min_agreeing.max(1)
+                && authenticated >= self.min_authenticated
+                && best.is_none_or(|b| group.len() > b.len())
+            {
+                best = Some(group);
+            }
+        }
+
+        let group = best.ok_or_else(|| VaultError::TimeUnavailable {
+            reason: format!(
+                "{} of the required {} time sources agreed within {}s",
+                largest_group(&sorted, self.max_skew_secs),
+                self.min_agreeing.max(1),
+                self.max_skew_secs
+            ),
+        })?;
+        let median = group[group.len() / 2].unix_secs;
+        Ok((median, group.iter().map(|s| s.source.clone()).collect()))
+    }
+}
+
+fn largest_group(sorted: &[&TimeSample], max_skew_secs: u64) -> usize {
+    (0..sorted.len())
+        .map(|start| {
+            sorted[start..]
+                .iter()
+                .take_while(|s| s.unix_secs - sorted[start].unix_secs <= max_skew_secs)
+                .count()
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Combines several [`TimeSource`]s under a [`TimePolicy`].
+pub struct TimeVerifier {
+    pub sources: Vec<Box<dyn TimeSource>>,
+    pub policy: TimePolicy,
+}
+
+impl Default for TimeVerifier {
+    /// Roughtime, HTTPS `Date` headers, the TLS JSON time APIs and NTP, with
+    /// the default policy of two agreeing sources including at least one
+    /// authenticated one.
+    fn default() -> Self {
+        let mut sources: Vec<Box<dyn TimeSource>> = vec![Box::new(RoughtimeClient::default())];
+        for source in HttpDateSource::defaults() {
+            sources.push(Box::new(source));
+        }
+        for api in JsonTimeApi::defaults() {
+            if api.url.starts_with("https://") {
+                sources.push(Box::new(api));
+            }
+        }
+        sources.push(Box::new(SntpSource::default()));
+        Self {
+            sources,
+            policy: TimePolicy::default(),
+        }
+    }
+}
+
+impl TimeVerifier {
+    pub fn new(sources: Vec<Box<dyn TimeSource>>, policy: TimePolicy) -> Self {
+        Self { sources, policy }
+    }
+
+    /// Queries all sources concurrently and returns every reading that
+    /// arrived within the policy's timeout.
+    pub async fn collect(&self) -> Vec<TimeSample> {
+        let fetches = self.sources.iter().map(|source| timeout(self.policy.timeout, source.fetch()));
+        join_all(fetches)
+            .await
+            .into_iter()
+            .filter_map(|r| r.ok().and_then(|r| r.ok()))
+            .collect()
+    }
+
+    /// Returns the policy-approved Unix time and a label naming the sources
+    /// that agreed on it.
+    pub async fn verify(&self) -> Result<(u64, String)> {
+        let samples = self.collect().await;
+        let (unix_secs, sources) = self.policy.decide(&samples)?;
+        Ok((unix_secs, sources.join(", ")))
+    }
+}
+
+/// Fetches the current Unix time with [`TimeVerifier::default`] and returns
+/// it with a label naming the sources that agreed on it.
+pub async fn fetch_verified_unixtime() -> Result<(u64, String)> {
+    TimeVerifier::default().verify().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    fn sample(unix_secs: u64, source: &str, authenticated: bool) -> TimeSample {
+        TimeSample {
+            unix_secs,
+            source: source.to_string(),
+            authenticated,
+        }
+    }
+
+    fn assert_unavailable(result: Result<(u64, Vec<String>)>) {
+        let err = VaultError::from(result.unwrap_err());
+        assert!(matches!(err, VaultError::TimeUnavailable { .. }), "{:?}", err);
+    }
+
+    #[test]
+    fn takes_the_median_of_agreeing_sources() {
+        let policy = TimePolicy::default();
+        let samples = [sample(1_000, "a", false), sample(1_030, "b", true), sample(1_010, "c", false)];
+        let (time, sources) = policy.decide(&samples).unwrap();
+        assert_eq!(time, 1_010);
+        assert_eq!(sources, ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn ignores_outliers() {
+        let policy = TimePolicy::default();
+        let samples = [sample(1_000, "a", true), sample(1_005, "b", false), sample(9_999_999, "liar", true)];
+        assert_eq!(policy.decide(&samples).unwrap(), (1_005, vec!["a".to_string(), "b".to_string()]));
+    }
+
+    #[test]
+    fn prefers_the_earliest_of_equal_groups() {
+        let policy = TimePolicy::default();
+        let samples = [sample(5_000, "c", true), sample(5_001, "d", true), sample(1_000, "a", true), sample(1_001, "b", true)];
+        assert_eq!(policy.decide(&samples).unwrap().0, 1_001);
+    }
+
+    #[test]
+    fn requires_enough_agreement() {
+        let policy = TimePolicy::default();
+        assert_unavailable(policy.decide(&[]));
+        assert_unavailable(policy.decide(&[sample(1_000, "a", true)]));
+        assert_unavailable(policy.decide(&[sample(1_000, "a", true), sample(1_061, "b", true)]));
+    }
+
+    #[test]
+    fn requires_an_authenticated_source() {
+        let policy = TimePolicy::default();
+        assert_unavailable(policy.decide(&[sample(1_000, "a", false), sample(1_001, "b", false), sample(1_002, "c", false)]));
+    }
+
+    struct Fixed(Result<u64, &'static str>);
+
+    impl TimeSource for Fixed {
+        fn name(&self) -> String {
+            "fixed".to_string()
+        }
+
+        fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>> {
+            let reading = self.0.map(|unix_secs| sample(unix_secs, "fixed", true)).map_err(|e| anyhow!(e));
+            Box::pin(async move { reading })
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_skips_failed_sources() {
+        let policy = TimePolicy {
+            min_agreeing: 2,
+            ..TimePolicy::default()
+        };
+        let verifier = TimeVerifier::new(vec![Box::new(Fixed(Ok(1_000))), Box::new(Fixed(Err("offline"))), Box::new(Fixed(Ok(1_002)))], policy);
+        assert_eq!(verifier.verify().await.unwrap(), (1_002, "fixed, fixed".to_string()));
+
+        let verifier = TimeVerifier::new(vec![Box::new(Fixed(Ok(1_000))), Box::new(Fixed(Err("offline")))], TimePolicy::default());
+        assert!(verifier.verify().await.is_err());
+    }
+}
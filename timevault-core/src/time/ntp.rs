@@ -0,0 +1,414 @@
+//! SNTP (RFC 4330) time source.
+//!
+//! Several servers are queried concurrently and the median of the clock
+//! readings that pass the sanity checks is used. A reply is rejected if it
+//! does not echo our transmit timestamp, comes from an unsynchronised or
+//! invalid stratum, or shows a round-trip delay or root distance too large
+//! to bound the error. Kiss-o'-death replies (stratum 0) are honoured:
+//! `DENY` and `RSTR` stop every source in the process from contacting the
+//! address that sent them again, and `RATE` pauses it for
+//! [`SntpSource::rate_backoff`]. Bans are kept per resolved address rather
+//! than per host name, so a pool name moves on to its other addresses and a
+//! server cannot be reached again under another name.
+//!
+//! NTP replies are not authenticated, so samples from this source never
+//! satisfy a [`TimePolicy`](super::TimePolicy) on their own.
+
+use anyhow::{anyhow, Result};
+use futures::future::join_all;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::time::timeout;
+
+use super::{BoxFuture, TimeSample, TimeSource};
+
+/// Seconds between the NTP era 0 epoch (1900) and the Unix epoch.
+const NTP_UNIX_OFFSET: i128 = 2_208_988_800;
+/// Length of an NTP era in seconds.
+const NTP_ERA_SECS: i128 = 1 << 32;
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+/// Length of an NTP packet without extensions.
+pub const PACKET_LEN: usize = 48;
+const MODE_CLIENT: u8 = 3;
+const MODE_SERVER: u8 = 4;
+const LEAP_UNSYNCHRONIZED: u8 = 3;
+const MAX_STRATUM: u8 = 15;
+
+/// Queries a set of NTP servers over SNTP.
+#[derive(Clone, Debug)]
+pub struct SntpSource {
+    /// `host:port` of each server, e.g. `pool.ntp.org:123`.
+    pub servers: Vec<String>,
+    /// How long to wait for each server's reply.
+    pub timeout: Duration,
+    /// Minimum number of servers that must return a usable reply.
+    pub min_responses: usize,
+    /// Largest accepted round-trip delay.
+    pub max_delay: Duration,
+    /// Largest accepted root distance (half the server's root delay plus its
+    /// root dispersion), i.e. the server's own error bound.
+    pub max_root_distance: Duration,
+    /// How long to leave a server alone after it sends a `RATE` kiss.
+    pub rate_backoff: Duration,
+}
+
+/// Addresses that sent a kiss-o'-death, mapped to when they may be contacted
+/// again (`None` means never). Shared by every [`SntpSource`] because each
+/// time check builds a fresh one.
+fn kissed() -> &'static Mutex<HashMap<SocketAddr, Option<Instant>>> {
+    static KISSED: OnceLock<Mutex<HashMap<SocketAddr, Option<Instant>>>> = OnceLock::new();
+    KISSED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn may_contact(address: SocketAddr) -> bool {
+    let kissed = kissed().lock().unwrap_or_else(|e| e.into_inner());
+    match kissed.get(&address) {
+        None => true,
+        Some(None) => false,
+        Some(Some(until)) => Instant::now() >= *until,
+    }
+}
+
+/// Outcome of querying one server.
+#[derive(Debug)]
+pub enum Reply {
+    /// Offset of the server's clock from ours, the round-trip delay and the
+    /// server's root distance, in nanoseconds.
+    Time { offset: i128, delay: i128, root_distance: i128 },
+    /// A kiss-o'-death with its four-letter code.
+    Kiss(String),
+}
+
+impl Default for SntpSource {
+    fn default() -> Self {
+        Self::new(
+            [
+                "0.pool.ntp.org:123",
+                "1.pool.ntp.org:123",
+                "2.pool.ntp.org:123",
+                "time.cloudflare.com:123",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        )
+    }
+}
+
+impl SntpSource {
+    pub fn new(servers: Vec<String>) -> Self {
+        Self {
+            servers,
+            timeout: Duration::from_secs(3),
+            min_responses: 2,
+            max_delay: Duration::from_secs(1),
+            max_root_distance: Duration::from_secs(1),
+            rate_backoff: Duration::from_secs(60),
+        }
+    }
+
+    fn record_kiss(&self, address: SocketAddr, code: &str) {
+        let until = match code {
+            "RATE" => Some(Instant::now() + self.rate_backoff),
+            "DENY" | "RSTR" => None,
+            _ => return,
+        };
+        let mut kissed = kissed().lock().unwrap_or_else(|e| e.into_inner());
+        kissed.insert(address, until);
+    }
+
+    /// Queries every server at an address that has not asked us to stay away
+    /// and returns the servers that answered usefully with their clock
+    /// offsets, in nanoseconds.
+    async fn query(&self) -> Vec<(String, i128)> {
+        let replies = join_all(self.servers.iter().map(|server| timeout(self.timeout, query_server(server)))).await;
+
+        let mut offsets = vec![];
+        for (server, reply) in self.servers.iter().zip(replies) {
+            match reply {
+                Ok(Ok((address, Reply::Kiss(code)))) => self.record_kiss(address, &code),
+                Ok(Ok((_, Reply::Time { offset, delay, root_distance })))
+                    if (0..=self.max_delay.as_nanos() as i128).contains(&delay)
+                        && root_distance <= self.max_root_distance.as_nanos() as i128 =>
+                {
+                    offsets.push((server.clone(), offset));
+                }
+                _ => {}
+            }
+        }
+        offsets
+    }
+}
+
+impl TimeSource for SntpSource {
+    fn name(&self) -> String {
+        "[NTP]".to_string()
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>> {
+        Box::pin(async move {
+            let mut offsets = self.query().await;
+            if offsets.len() < self.min_responses.max(1) {
+                return Err(anyhow!(
+                    "{} of {} required NTP servers answered",
+                    offsets.len(),
+                    self.min_responses.max(1)
+                ));
+            }
+            offsets.sort_by_key(|(_, offset)| *offset);
+            let median = offsets[offsets.len() / 2].1;
+
+            let unix_nanos = unix_now_nanos() + median;
+            let unix_secs = u64::try_from(unix_nanos / NANOS_PER_SEC).map_err(|_| anyhow!("NTP time is before 1970"))?;
+            let names: Vec<&str> = offsets.iter().map(|(name, _)| name.as_str()).collect();
+            Ok(TimeSample {
+                unix_secs,
+                source: format!("[NTP: {}]", names.join(", ")),
+                authenticated: false,
+            })
+        })
+    }
+}
+
+/// Queries the first address of `server` that has not sent a kiss-o'-death
+/// and returns it with the reply. The socket is connected, so the reply came
+/// from that address.
+async fn query_server(server: &str) -> Result<(SocketAddr, Reply)> {
+    let mut addresses = lookup_host(server).await?.peekable();
+    if addresses.peek().is_none() {
+        return Err(anyhow!("Could not resolve {}", server));
+    }
+    let target = addresses
+        .find(|address| may_contact(*address))
+        .ok_or_else(|| anyhow!("{} asked us to stay away", server))?;
+    let bind = if target.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(target).await?;
+
+    // The transmit timestamp is random rather than our clock so that it works
+    // as a nonce; the server must echo it back as the originate timestamp.
+    let mut request = [0u8; PACKET_LEN];
+    request[0] = (4 << 3) | MODE_CLIENT;
+    OsRng.fill_bytes(&mut request[40..48]);
+
+    let t1 = unix_now_nanos();
+    socket.send(&request).await?;
+    let mut buf = [0u8; PACKET_LEN + 64];
+    let len = socket.recv(&mut buf).await?;
+    let t4 = unix_now_nanos();
+
+    if len < PACKET_LEN {
+        return Err(anyhow!("{} sent a short NTP reply", server));
+    }
+    let mut reply = [0u8; PACKET_LEN];
+    reply.copy_from_slice(&buf[..PACKET_LEN]);
+    let reply = parse_reply(&request, &reply, t1, t4).map_err(|e| anyhow!("{}: {}", server, e))?;
+    Ok((target, reply))
+}
+
+/// Validates `reply` against `request` and computes the clock offset and
+/// round-trip delay from the local send and receive times `t1` and `t4`
+/// (Unix nanoseconds).
+pub fn parse_reply(request: &[u8; PACKET_LEN], reply: &[u8; PACKET_LEN], t1: i128, t4: i128) -> Result<Reply> {
+    let leap = reply[0] >> 6;
+    let version = (reply[0] >> 3) & 0b111;
+    let mode = reply[0] & 0b111;
+    let stratum = reply[1];
+
+    if mode != MODE_SERVER || !(1..=4).contains(&version) {
+        return Err(anyhow!("not an NTP server reply"));
+    }
+    if reply[24..32] != request[40..48] {
+        return Err(anyhow!("reply does not match the request"));
+    }
+    if stratum == 0 {
+        return Ok(Reply::Kiss(String::from_utf8_lossy(&reply[12..16]).into_owned()));
+    }
+    if leap == LEAP_UNSYNCHRONIZED || stratum > MAX_STRATUM {
+        return Err(anyhow!("server clock is not synchronised"));
+    }
+    if reply[40..48].iter().all(|b| *b == 0) {
+        return Err(anyhow!("reply has no transmit timestamp"));
+    }
+
+    let t2 = timestamp_to_unix_nanos(&reply[32..40], t1);
+    let t3 = timestamp_to_unix_nanos(&reply[40..48], t4);
+    Ok(Reply::Time {
+        offset: ((t2 - t1) + (t3 - t4)) / 2,
+        delay: (t4 - t1) - (t3 - t2),
+        root_distance: short_to_nanos(&reply[4..8]) / 2 + short_to_nanos(&reply[8..12]),
+    })
+}
+
+/// Converts a 64-bit NTP timestamp to Unix nanoseconds, choosing the NTP era
+/// that puts it closest to `near` (Unix nanoseconds) so that readings stay
+/// correct across the 2036 era rollover.
+fn timestamp_to_unix_nanos(bytes: &[u8], near: i128) -> i128 {
+    let secs = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i128;
+    let frac = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as i128;
+
+    let near_secs = near / NANOS_PER_SEC + NTP_UNIX_OFFSET;
+    let era = (near_secs - secs + NTP_ERA_SECS / 2).div_euclid(NTP_ERA_SECS);
+    let ntp_secs = era * NTP_ERA_SECS + secs;
+    (ntp_secs - NTP_UNIX_OFFSET) * NANOS_PER_SEC + ((frac * NANOS_PER_SEC) >> 32)
+}
+
+/// Converts an NTP short (16.16 fixed point seconds) to nanoseconds.
+fn short_to_nanos(bytes: &[u8]) -> i128 {
+    let value = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i128;
+    (value * NANOS_PER_SEC) >> 16
+}
+
+fn unix_now_nanos() -> i128 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_nanos() as i128,
+        Err(e) => -(e.duration().as_nanos() as i128),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER_OFFSET: i128 = 100 * NANOS_PER_SEC;
+
+    fn ntp_timestamp(unix_nanos: i128) -> [u8; 8] {
+        let secs = (unix_nanos.div_euclid(NANOS_PER_SEC) + NTP_UNIX_OFFSET).rem_euclid(NTP_ERA_SECS) as u32;
+        let frac = ((unix_nanos.rem_euclid(NANOS_PER_SEC) << 32) / NANOS_PER_SEC) as u32;
+        let mut out = [0u8; 8];
+        out[..4].copy_from_slice(&secs.to_be_bytes());
+        out[4..].copy_from_slice(&frac.to_be_bytes());
+        out
+    }
+
+    /// A well-formed stratum 2 reply from a server whose clock runs
+    /// [`SERVER_OFFSET`] ahead of ours.
+    fn good_reply(request: &[u8; PACKET_LEN]) -> [u8; PACKET_LEN] {
+        let mut reply = [0u8; PACKET_LEN];
+        reply[0] = (4 << 3) | MODE_SERVER;
+        reply[1] = 2;
+        reply[24..32].copy_from_slice(&request[40..48]);
+        let now = ntp_timestamp(unix_now_nanos() + SERVER_OFFSET);
+        reply[32..40].copy_from_slice(&now);
+        reply[40..48].copy_from_slice(&now);
+        reply
+    }
+
+    /// Binds a local stand-in server that answers one request with
+    /// [`good_reply`] after passing it through `tweak`, and returns its
+    /// address.
+    async fn stand_in(tweak: fn(&mut [u8; PACKET_LEN])) -> String {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut request = [0u8; PACKET_LEN];
+            let (_, peer) = socket.recv_from(&mut request).await.unwrap();
+            let mut reply = good_reply(&request);
+            tweak(&mut reply);
+            socket.send_to(&reply, peer).await.unwrap();
+        });
+        address
+    }
+
+    /// Sending two seconds before receiving makes the computed round trip
+    /// two seconds longer than it was.
+    fn send_before_receive(reply: &mut [u8; PACKET_LEN]) {
+        let secs = u32::from_be_bytes([reply[40], reply[41], reply[42], reply[43]]) - 2;
+        reply[40..44].copy_from_slice(&secs.to_be_bytes());
+    }
+
+    fn kiss(reply: &mut [u8; PACKET_LEN], code: &[u8; 4]) {
+        reply[1] = 0;
+        reply[12..16].copy_from_slice(code);
+    }
+
+    fn kiss_deny(reply: &mut [u8; PACKET_LEN]) {
+        kiss(reply, b"DENY");
+    }
+
+    fn kiss_rate(reply: &mut [u8; PACKET_LEN]) {
+        kiss(reply, b"RATE");
+    }
+
+    fn source(address: &str) -> SntpSource {
+        SntpSource {
+            timeout: Duration::from_secs(1),
+            min_responses: 1,
+            ..SntpSource::new(vec![address.to_string()])
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_a_good_reply() {
+        let address = stand_in(|_| {}).await;
+        let sample = source(&address).fetch().await.unwrap();
+
+        let expected = (unix_now_nanos() + SERVER_OFFSET) / NANOS_PER_SEC;
+        assert!((sample.unix_secs as i128 - expected).abs() <= 1, "{} vs {}", sample.unix_secs, expected);
+        assert_eq!(sample.source, format!("[NTP: {}]", address));
+        assert!(!sample.authenticated);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_reply_to_another_request() {
+        let address = stand_in(|reply| reply[31] ^= 1).await;
+        let err = query_server(&address).await.unwrap_err();
+        assert!(err.to_string().contains("does not match the request"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn rejects_unsynchronised_servers() {
+        let address = stand_in(|reply| reply[0] |= LEAP_UNSYNCHRONIZED << 6).await;
+        assert!(query_server(&address).await.is_err());
+        let address = stand_in(|reply| reply[1] = MAX_STRATUM + 1).await;
+        assert!(query_server(&address).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_too_much_delay() {
+        let address = stand_in(send_before_receive).await;
+        let (_, Reply::Time { delay, .. }) = query_server(&address).await.unwrap() else {
+            panic!("expected a time reply");
+        };
+        assert!(delay > source(&address).max_delay.as_nanos() as i128);
+
+        let address = stand_in(send_before_receive).await;
+        assert!(source(&address).fetch().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_too_much_root_distance() {
+        // A root dispersion of two seconds, in NTP short format.
+        let address = stand_in(|reply| reply[8..12].copy_from_slice(&(2u32 << 16).to_be_bytes())).await;
+        assert!(source(&address).fetch().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn honours_kiss_o_death_across_sources() {
+        let address = stand_in(kiss_deny).await;
+        let (from, reply) = query_server(&address).await.unwrap();
+        assert_eq!(from.to_string(), address);
+        assert!(matches!(reply, Reply::Kiss(code) if code == "DENY"));
+
+        let address = stand_in(kiss_deny).await;
+        assert!(source(&address).fetch().await.is_err());
+        assert!(!may_contact(address.parse().unwrap()));
+        let err = query_server(&address).await.unwrap_err();
+        assert!(err.to_string().contains("asked us to stay away"), "{}", err);
+
+        let address = stand_in(kiss_rate).await;
+        let rate_limited = SntpSource {
+            rate_backoff: Duration::from_millis(200),
+            ..source(&address)
+        };
+        assert!(rate_limited.fetch().await.is_err());
+        assert!(!may_contact(address.parse().unwrap()));
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(may_contact(address.parse().unwrap()));
+    }
+}
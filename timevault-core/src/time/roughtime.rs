@@ -0,0 +1,596 @@
+//! Roughtime client.
+//!
+//! Roughtime servers answer a client-chosen nonce with a timestamp signed by
+//! a short-lived delegated key, which is in turn signed by the server's
+//! long-term key. Responses are only accepted if that chain verifies against
+//! a public key pinned in [`RoughtimeServer`], so a network attacker cannot
+//! forge the time. Servers are queried in sequence with each nonce derived
+//! from the previous reply, so the replies form a causally ordered chain and
+//! a server reporting a time inconsistent with an earlier one is detected.
+//!
+//! This implements the original Google Roughtime wire format (UDP, 1024-byte
+//! requests, SHA-512 Merkle tree), which the public servers listed in
+//! [`RoughtimeServer::defaults`] speak.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Signature, VerifyingKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::{Digest, Sha512};
+use std::time::Duration;
+use tokio::net::{lookup_host, UdpSocket};
+use tokio::time::timeout;
+
+use super::{BoxFuture, TimeSample, TimeSource};
+
+const REQUEST_SIZE: usize = 1024;
+/// Length of the nonce a client sends.
+pub const NONCE_LEN: usize = 64;
+const MAX_RESPONSE_SIZE: usize = 4096;
+
+const RESPONSE_CONTEXT: &[u8] = b"RoughTime v1 response signature\0";
+const DELEGATION_CONTEXT: &[u8] = b"RoughTime v1 delegation signature--\0";
+
+const fn tag(name: &[u8; 4]) -> u32 {
+    u32::from_le_bytes(*name)
+}
+
+const TAG_NONC: u32 = tag(b"NONC");
+const TAG_PAD: u32 = tag(b"PAD\xff");
+const TAG_SIG: u32 = tag(b"SIG\x00");
+const TAG_SREP: u32 = tag(b"SREP");
+const TAG_CERT: u32 = tag(b"CERT");
+const TAG_INDX: u32 = tag(b"INDX");
+const TAG_PATH: u32 = tag(b"PATH");
+const TAG_ROOT: u32 = tag(b"ROOT");
+const TAG_MIDP: u32 = tag(b"MIDP");
+const TAG_RADI: u32 = tag(b"RADI");
+const TAG_DELE: u32 = tag(b"DELE");
+const TAG_PUBK: u32 = tag(b"PUBK");
+const TAG_MINT: u32 = tag(b"MINT");
+const TAG_MAXT: u32 = tag(b"MAXT");
+
+/// A Roughtime server and the long-term Ed25519 key its responses must be
+/// signed with.
+#[derive(Clone, Debug)]
+pub struct RoughtimeServer {
+    pub name: String,
+    /// `host:port` of the server's UDP endpoint.
+    pub address: String,
+    pub public_key: [u8; 32],
+}
+
+impl RoughtimeServer {
+    /// Builds a server entry from a base64-encoded public key.
+    pub fn new(name: &str, address: &str, public_key_b64: &str) -> Result<Self> {
+        let key = general_purpose::STANDARD.decode(public_key_b64)?;
+        let public_key: [u8; 32] = key
+            .try_into()
+            .map_err(|_| anyhow!("Roughtime public key for {} must be 32 bytes", name))?;
+        Ok(Self {
+            name: name.to_string(),
+            address: address.to_string(),
+            public_key,
+        })
+    }
+
+    /// Public servers from the Roughtime ecosystem list, with their pinned
+    /// long-term keys.
+    pub fn defaults() -> Vec<Self> {
+        [
+            ("Cloudflare", "roughtime.cloudflare.com:2002", "gD63hSj3ScS+wuOeGrubXlq35N1c5Lby/S+T7MNTjxo="),
+            ("int08h", "roughtime.int08h.com:2002", "AW5uAoTSTDfG5NfY1bTh08GUnOqlRb+HVhbJ3ODJvsE="),
+            ("Google Sandbox", "roughtime.sandbox.google.com:2002", "etPaaIxcBMY1oUeGpwvPMCJMwlRVNxv51KK/tktoJTQ="),
+        ]
+        .iter()
+        .filter_map(|(name, address, key)| Self::new(name, address, key).ok())
+        .collect()
+    }
+}
+
+/// A verified answer from one server.
+#[derive(Clone, Debug)]
+pub struct RoughtimeReading {
+    pub server: String,
+    /// Midpoint of the server's time interval, in microseconds since the
+    /// Unix epoch.
+    pub midpoint_us: u64,
+    /// Half-width of the server's uncertainty interval, in microseconds.
+    pub radius_us: u32,
+}
+
+/// Queries a chain of Roughtime servers.
+#[derive(Clone, Debug)]
+pub struct RoughtimeClient {
+    pub servers: Vec<RoughtimeServer>,
+    /// How long to wait for each server's reply.
+    pub timeout: Duration,
+    /// Minimum number of servers that must return a verified reply.
+    pub min_responses: usize,
+}
+
+impl Default for RoughtimeClient {
+    fn default() -> Self {
+        Self {
+            servers: RoughtimeServer::defaults(),
+            timeout: Duration::from_secs(3),
+            min_responses: 1,
+        }
+    }
+}
+
+impl RoughtimeClient {
+    /// Queries every configured server in turn and returns the verified
+    /// replies in chain order.
+    ///
+    /// Servers that do not answer or whose reply fails verification are
+    /// skipped. Fails if fewer than `min_responses` replies verify, or if two
+    /// replies are causally inconsistent (a later server reporting a time
+    /// entirely before an earlier one).
+    pub async fn query(&self) -> Result<Vec<RoughtimeReading>> {
+        let mut readings: Vec<RoughtimeReading> = vec![];
+        let mut previous_reply: Option<Vec<u8>> = None;
+
+        for server in &self.servers {
+            let nonce = chained_nonce(previous_reply.as_deref());
+            let reply = match timeout(self.timeout, exchange(&server.address, &build_request(&nonce))).await {
+                Ok(Ok(reply)) => reply,
+                _ => continue,
+            };
+            let Ok((midpoint_us, radius_us)) = verify_response(&server.public_key, &nonce, &reply) else {
+                continue;
+            };
+
+            if let Some(earlier) = readings.last() {
+                let latest_now = midpoint_us.saturating_add(radius_us as u64);
+                let earliest_before = earlier.midpoint_us.saturating_sub(earlier.radius_us as u64);
+                if latest_now < earliest_before {
+                    return Err(anyhow!(
+                        "Roughtime servers {} and {} report inconsistent times",
+                        earlier.server,
+                        server.name
+                    ));
+                }
+            }
+
+            readings.push(RoughtimeReading {
+                server: server.name.clone(),
+                midpoint_us,
+                radius_us,
+            });
+            previous_reply = Some(reply);
+        }
+
+        if readings.len() < self.min_responses.max(1) {
+            return Err(anyhow!(
+                "Date and time verification failed: {} of {} required Roughtime servers answered",
+                readings.len(),
+                self.min_responses.max(1)
+            ));
+        }
+        Ok(readings)
+    }
+
+    /// Returns the median verified time in Unix seconds, labelled with the
+    /// servers that vouched for it.
+    pub async fn fetch_unixtime(&self) -> Result<(u64, String)> {
+        let readings = self.query().await?;
+        let mut midpoints: Vec<u64> = readings.iter().map(|r| r.midpoint_us).collect();
+        midpoints.sort_unstable();
+        let median = midpoints[midpoints.len() / 2];
+
+        let names: Vec<&str> = readings.iter().map(|r| r.server.as_str()).collect();
+        Ok((median / 1_000_000, format!("[Roughtime: {}]", names.join(", "))))
+    }
+}
+
+impl TimeSource for RoughtimeClient {
+    fn name(&self) -> String {
+        "[Roughtime]".to_string()
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>> {
+        Box::pin(async move {
+            let (unix_secs, source) = self.fetch_unixtime().await?;
+            Ok(TimeSample {
+                unix_secs,
+                source,
+                authenticated: true,
+            })
+        })
+    }
+}
+
+/// First nonce is random; each later one is `SHA-512(previous reply || blind)`
+/// so that it commits to everything received so far.
+fn chained_nonce(previous_reply: Option<&[u8]>) -> [u8; NONCE_LEN] {
+    let mut blind = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut blind);
+    let Some(previous) = previous_reply else {
+        return blind;
+    };
+
+    let mut hasher = Sha512::new();
+    hasher.update(previous);
+    hasher.update(blind);
+    hasher.finalize().into()
+}
+
+async fn exchange(address: &str, request: &[u8]) -> Result<Vec<u8>> {
+    let target = lookup_host(address)
+        .await?
+        .next()
+        .ok_or_else(|| anyhow!("Could not resolve {}", address))?;
+    let bind = if target.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(target).await?;
+    socket.send(request).await?;
+
+    let mut buf = vec![0u8; MAX_RESPONSE_SIZE];
+    let len = socket.recv(&mut buf).await?;
+    buf.truncate(len);
+    Ok(buf)
+}
+
+/// Encodes a Roughtime message. `fields` must be sorted by tag and every
+/// value a multiple of four bytes long.
+pub(crate) fn encode_message(fields: &[(u32, &[u8])]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+    let mut offset = 0u32;
+    for (_, value) in fields.iter().take(fields.len().saturating_sub(1)) {
+        offset += value.len() as u32;
+        out.extend_from_slice(&offset.to_le_bytes());
+    }
+    for (tag, _) in fields {
+        out.extend_from_slice(&tag.to_le_bytes());
+    }
+    for (_, value) in fields {
+        out.extend_from_slice(value);
+    }
+    out
+}
+
+pub(crate) fn build_request(nonce: &[u8; NONCE_LEN]) -> Vec<u8> {
+    let header_len = 4 + 4 + 2 * 4;
+    let padding = vec![0u8; REQUEST_SIZE - header_len - NONCE_LEN];
+    encode_message(&[(TAG_NONC, nonce), (TAG_PAD, &padding)])
+}
+
+/// A parsed Roughtime message: tags in ascending order with their values.
+pub struct Message<'a> {
+    fields: Vec<(u32, &'a [u8])>,
+}
+
+impl<'a> Message<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let read_u32 = |at: usize| -> Result<u32> {
+            bytes
+                .get(at..at + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or_else(|| anyhow!("Truncated Roughtime message"))
+        };
+
+        let num_tags = read_u32(0)? as usize;
+        if num_tags == 0 || num_tags > 64 {
+            return Err(anyhow!("Invalid Roughtime tag count"));
+        }
+        let header_len = 4 + (num_tags - 1) * 4 + num_tags * 4;
+        if bytes.len() < header_len || !bytes.len().is_multiple_of(4) {
+            return Err(anyhow!("Truncated Roughtime message"));
+        }
+        let values_len = bytes.len() - header_len;
+
+        let mut offsets = vec![0usize];
+        for i in 0..num_tags - 1 {
+            offsets.push(read_u32(4 + i * 4)? as usize);
+        }
+        offsets.push(values_len);
+
+        let mut fields = Vec::with_capacity(num_tags);
+        let mut previous_tag = None;
+        for i in 0..num_tags {
+            let tag = read_u32(4 + (num_tags - 1) * 4 + i * 4)?;
+            if previous_tag.is_some_and(|p| tag <= p) {
+                return Err(anyhow!("Roughtime tags are not in ascending order"));
+            }
+            previous_tag = Some(tag);
+
+            let (start, end) = (offsets[i], offsets[i + 1]);
+            if !start.is_multiple_of(4) || end < start || end > values_len {
+                return Err(anyhow!("Invalid Roughtime value offset"));
+            }
+            fields.push((tag, &bytes[header_len + start..header_len + end]));
+        }
+        Ok(Self { fields })
+    }
+
+    /// The value of `tag`, which is a little-endian four-byte name such as
+    /// `u32::from_le_bytes(*b"NONC")`.
+    pub fn get(&self, tag: u32) -> Result<&'a [u8]> {
+        self.fields
+            .iter()
+            .find(|(t, _)| *t == tag)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| anyhow!("Roughtime message is missing tag {:?}", String::from_utf8_lossy(&tag.to_le_bytes())))
+    }
+
+    fn get_fixed<const N: usize>(&self, tag: u32) -> Result<[u8; N]> {
+        self.get(tag)?
+            .try_into()
+            .map_err(|_| anyhow!("Roughtime tag {:?} has the wrong length", String::from_utf8_lossy(&tag.to_le_bytes())))
+    }
+
+    fn get_u32(&self, tag: u32) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.get_fixed(tag)?))
+    }
+
+    fn get_u64(&self, tag: u32) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.get_fixed(tag)?))
+    }
+}
+
+fn verify_signature(public_key: &[u8; 32], context: &[u8], message: &[u8], signature: [u8; 64]) -> Result<()> {
+    let key = VerifyingKey::from_bytes(public_key).map_err(|_| anyhow!("Invalid Roughtime public key"))?;
+    let mut signed = Vec::with_capacity(context.len() + message.len());
+    signed.extend_from_slice(context);
+    signed.extend_from_slice(message);
+    key.verify_strict(&signed, &Signature::from_bytes(&signature))
+        .map_err(|_| anyhow!("Roughtime signature verification failed"))
+}
+
+/// Verifies a reply to `nonce` against the server's long-term key and
+/// returns its `(midpoint, radius)` in microseconds.
+pub fn verify_response(public_key: &[u8; 32], nonce: &[u8; NONCE_LEN], reply: &[u8]) -> Result<(u64, u32)> {
+    let response = Message::parse(reply)?;
+
+    let cert = Message::parse(response.get(TAG_CERT)?)?;
+    let dele_bytes = cert.get(TAG_DELE)?;
+    verify_signature(public_key, DELEGATION_CONTEXT, dele_bytes, cert.get_fixed(TAG_SIG)?)?;
+    let dele = Message::parse(dele_bytes)?;
+    let delegated_key: [u8; 32] = dele.get_fixed(TAG_PUBK)?;
+
+    let srep_bytes = response.get(TAG_SREP)?;
+    verify_signature(&delegated_key, RESPONSE_CONTEXT, srep_bytes, response.get_fixed(TAG_SIG)?)?;
+    let srep = Message::parse(srep_bytes)?;
+
+    let root: [u8; 64] = srep.get_fixed(TAG_ROOT)?;
+    let mut index = response.get_u32(TAG_INDX)?;
+    let path = response.get(TAG_PATH)?;
+    if !path.len().is_multiple_of(64) || path.len() / 64 > 32 {
+        return Err(anyhow!("Invalid Roughtime Merkle path"));
+    }
+    let mut hash = hash_leaf(nonce);
+    for sibling in path.chunks(64) {
+        hash = if index & 1 == 0 { hash_node(&hash, sibling) } else { hash_node(sibling, &hash) };
+        index >>= 1;
+    }
+    if index != 0 || hash != root {
+        return Err(anyhow!("Roughtime reply does not cover our nonce"));
+    }
+
+    let midpoint_us = srep.get_u64(TAG_MIDP)?;
+    let radius_us = srep.get_u32(TAG_RADI)?;
+    let (min_t, max_t) = (dele.get_u64(TAG_MINT)?, dele.get_u64(TAG_MAXT)?);
+    if midpoint_us < min_t || midpoint_us > max_t {
+        return Err(anyhow!("Roughtime delegation is not valid at the reported time"));
+    }
+
+    Ok((midpoint_us, radius_us))
+}
+
+pub(crate) fn hash_leaf(data: &[u8]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update([0u8]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub(crate) fn hash_node(left: &[u8], right: &[u8]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update([1u8]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+    use proptest::prelude::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    const LONG_TERM_KEY: [u8; 32] = [7; 32];
+    const DELEGATED_KEY: [u8; 32] = [9; 32];
+    const RADIUS_US: u32 = 1_000_000;
+    const HOUR_US: u64 = 3_600_000_000;
+
+    /// What a stand-in server gets wrong in its reply.
+    #[derive(Clone, Copy)]
+    enum Fault {
+        None,
+        /// The delegation is signed by a key other than the pinned one.
+        ForeignKey,
+        /// The response signature is corrupted.
+        BadSignature,
+        /// The Merkle tree is built over a different nonce.
+        OtherNonce,
+        /// The delegation expired before the reported midpoint.
+        Expired,
+    }
+
+    fn now_us() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_micros() as u64
+    }
+
+    /// Encodes a message from fields in any order.
+    fn message(mut fields: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+        fields.sort_by_key(|(tag, _)| *tag);
+        let borrowed: Vec<(u32, &[u8])> = fields.iter().map(|(tag, value)| (*tag, value.as_slice())).collect();
+        encode_message(&borrowed)
+    }
+
+    fn sign(key: &[u8; 32], context: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut signed = context.to_vec();
+        signed.extend_from_slice(message);
+        SigningKey::from_bytes(key).sign(&signed).to_bytes().to_vec()
+    }
+
+    /// Builds a reply to `nonce` in a two-leaf Merkle tree, reporting
+    /// `midpoint_us`.
+    fn reply(nonce: &[u8], midpoint_us: u64, fault: Fault) -> Vec<u8> {
+        let long_term_key = if matches!(fault, Fault::ForeignKey) { [8; 32] } else { LONG_TERM_KEY };
+        let max_t = if matches!(fault, Fault::Expired) { midpoint_us - 1 } else { midpoint_us + HOUR_US };
+        let dele = message(vec![
+            (TAG_PUBK, SigningKey::from_bytes(&DELEGATED_KEY).verifying_key().to_bytes().to_vec()),
+            (TAG_MINT, (midpoint_us - HOUR_US).to_le_bytes().to_vec()),
+            (TAG_MAXT, max_t.to_le_bytes().to_vec()),
+        ]);
+        let cert = message(vec![(TAG_SIG, sign(&long_term_key, DELEGATION_CONTEXT, &dele)), (TAG_DELE, dele)]);
+
+        let leaf = if matches!(fault, Fault::OtherNonce) { hash_leaf(&[0xee; NONCE_LEN]) } else { hash_leaf(nonce) };
+        let sibling = hash_leaf(&[0x11; NONCE_LEN]);
+        let srep = message(vec![
+            (TAG_ROOT, hash_node(&leaf, &sibling).to_vec()),
+            (TAG_MIDP, midpoint_us.to_le_bytes().to_vec()),
+            (TAG_RADI, RADIUS_US.to_le_bytes().to_vec()),
+        ]);
+        let mut signature = sign(&DELEGATED_KEY, RESPONSE_CONTEXT, &srep);
+        if matches!(fault, Fault::BadSignature) {
+            signature[0] ^= 1;
+        }
+
+        message(vec![
+            (TAG_SIG, signature),
+            (TAG_PATH, sibling.to_vec()),
+            (TAG_SREP, srep),
+            (TAG_CERT, cert),
+            (TAG_INDX, 0u32.to_le_bytes().to_vec()),
+        ])
+    }
+
+    /// Binds a local stand-in server that answers one request and returns
+    /// its entry, pinned to the test long-term key.
+    async fn stand_in(name: &str, midpoint_us: u64, fault: Fault) -> RoughtimeServer {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut request = vec![0u8; MAX_RESPONSE_SIZE];
+            let (len, peer) = socket.recv_from(&mut request).await.unwrap();
+            assert_eq!(len, REQUEST_SIZE);
+            let nonce = Message::parse(&request[..len]).unwrap().get(TAG_NONC).unwrap().to_vec();
+            socket.send_to(&reply(&nonce, midpoint_us, fault), peer).await.unwrap();
+        });
+        RoughtimeServer {
+            name: name.to_string(),
+            address,
+            public_key: SigningKey::from_bytes(&LONG_TERM_KEY).verifying_key().to_bytes(),
+        }
+    }
+
+    fn client(servers: Vec<RoughtimeServer>) -> RoughtimeClient {
+        RoughtimeClient {
+            servers,
+            timeout: Duration::from_secs(1),
+            min_responses: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_a_valid_reply() {
+        let midpoint_us = now_us();
+        let client = client(vec![stand_in("local", midpoint_us, Fault::None).await]);
+        let readings = client.query().await.unwrap();
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].server, "local");
+        assert_eq!((readings[0].midpoint_us, readings[0].radius_us), (midpoint_us, RADIUS_US));
+    }
+
+    #[tokio::test]
+    async fn reports_the_median_of_a_chain() {
+        let midpoint_us = now_us();
+        let client = client(vec![
+            stand_in("a", midpoint_us, Fault::None).await,
+            stand_in("b", midpoint_us + 5_000_000, Fault::None).await,
+            stand_in("c", midpoint_us + 9_000_000, Fault::None).await,
+        ]);
+        let (unix_secs, source) = client.fetch_unixtime().await.unwrap();
+        assert_eq!(unix_secs, (midpoint_us + 5_000_000) / 1_000_000);
+        assert_eq!(source, "[Roughtime: a, b, c]");
+    }
+
+    #[tokio::test]
+    async fn rejects_faulty_replies() {
+        for fault in [Fault::ForeignKey, Fault::BadSignature, Fault::OtherNonce, Fault::Expired] {
+            let client = client(vec![stand_in("local", now_us(), fault).await]);
+            let err = client.query().await.unwrap_err();
+            assert!(err.to_string().contains("0 of 1"), "{}", err);
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_faulty_servers_in_a_chain() {
+        let client = client(vec![stand_in("bad", now_us(), Fault::BadSignature).await, stand_in("good", now_us(), Fault::None).await]);
+        let readings = client.query().await.unwrap();
+        assert_eq!(readings.iter().map(|r| r.server.as_str()).collect::<Vec<_>>(), ["good"]);
+    }
+
+    #[tokio::test]
+    async fn detects_an_inconsistent_chain() {
+        let midpoint_us = now_us();
+        let client = client(vec![stand_in("early", midpoint_us, Fault::None).await, stand_in("late", midpoint_us - HOUR_US, Fault::None).await]);
+        let err = client.query().await.unwrap_err();
+        assert!(err.to_string().contains("early and late report inconsistent times"), "{}", err);
+    }
+
+    #[test]
+    fn names_what_is_wrong_with_a_reply() {
+        let public_key = SigningKey::from_bytes(&LONG_TERM_KEY).verifying_key().to_bytes();
+        let nonce = [0x42; NONCE_LEN];
+        for (fault, reason) in [
+            (Fault::ForeignKey, "signature verification failed"),
+            (Fault::BadSignature, "signature verification failed"),
+            (Fault::OtherNonce, "does not cover our nonce"),
+            (Fault::Expired, "not valid at the reported time"),
+        ] {
+            let err = verify_response(&public_key, &nonce, &reply(&nonce, now_us(), fault)).unwrap_err();
+            assert!(err.to_string().contains(reason), "{}", err);
+        }
+    }
+
+    #[test]
+    fn verifies_replies_directly() {
+        let public_key = SigningKey::from_bytes(&LONG_TERM_KEY).verifying_key().to_bytes();
+        let nonce = [0x42; NONCE_LEN];
+        let midpoint_us = now_us();
+        assert_eq!(verify_response(&public_key, &nonce, &reply(&nonce, midpoint_us, Fault::None)).unwrap(), (midpoint_us, RADIUS_US));
+        assert!(verify_response(&public_key, &[0x43; NONCE_LEN], &reply(&nonce, midpoint_us, Fault::None)).is_err());
+
+        let valid = reply(&nonce, midpoint_us, Fault::None);
+        for cut in [0, 4, valid.len() / 2, valid.len() - 4] {
+            assert!(verify_response(&public_key, &nonce, &valid[..cut]).is_err());
+        }
+    }
+
+    #[test]
+    fn requests_are_a_fixed_size() {
+        let nonce = [0x42; NONCE_LEN];
+        let request = build_request(&nonce);
+        assert_eq!(request.len(), REQUEST_SIZE);
+        assert_eq!(Message::parse(&request).unwrap().get(TAG_NONC).unwrap(), nonce);
+    }
+
+    proptest! {
+        #[test]
+        fn verify_rejects_any_modification(at in any::<prop::sample::Index>(), flip in 1..=u8::MAX) {
+            let public_key = SigningKey::from_bytes(&LONG_TERM_KEY).verifying_key().to_bytes();
+            let nonce = [0x42; NONCE_LEN];
+            let mut modified = reply(&nonce, 1_700_000_000_000_000, Fault::None);
+            let i = at.index(modified.len());
+            modified[i] ^= flip;
+            prop_assert!(verify_response(&public_key, &nonce, &modified).is_err());
+        }
+    }
+}
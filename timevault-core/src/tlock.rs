@@ -0,0 +1,400 @@
+//! drand time-lock encryption.
+//!
+//! A drand network publishes, every `period` seconds, a BLS signature over
+//! the round number. With the `bls-unchained-g1-rfc9380` scheme (the
+//! "quicknet" chain) that signature is `s·H(round)` in G1, where `s` is the
+//! network's threshold secret key and `H` hashes to G1. Such a signature is
+//! exactly the Boneh–Franklin IBE private key for the identity `H(round)`,
+//! so anything encrypted to that identity under the network's public key
+//! cannot be decrypted until the network publishes the round, whatever the
+//! local clock or any time server says. This is the tlock construction.
+//!
+//! Time-locked entries use this to encrypt their content key. Beacons are
+//! fetched from public HTTP relays but never trusted as delivered: every
+//! signature is verified offline against the [`DrandChainInfo`] stored in
+//! the entry's (FEK-encrypted, authenticated) metadata.
+
+use anyhow::{anyhow, Result};
+use ark_bls12_381::{g1, Bls12_381, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::hashing::curve_maps::wb::WBMap;
+use ark_ec::hashing::map_to_curve_hasher::MapToCurveBasedHasher;
+use ark_ec::hashing::HashToCurve;
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::field_hashers::DefaultFieldHasher;
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use base64::{engine::general_purpose, Engine as _};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+use zeroize::Zeroize;
+
+use crate::error::VaultError;
+
+/// The only drand signature scheme supported for time-lock encryption.
+pub const SCHEME_ID: &str = "bls-unchained-g1-rfc9380";
+
+const DST_G1: &[u8] = b"BLS_SIG_BLS12381G1_XMD:SHA-256_SSWU_RO_NUL_";
+const G2_COMPRESSED_LEN: usize = 96;
+const KEY_LEN: usize = 32;
+const CIPHERTEXT_LEN: usize = G2_COMPRESSED_LEN + 2 * KEY_LEN;
+/// HTTP status relays return for rounds that have not been published yet.
+const TOO_EARLY: u16 = 425;
+
+/// Public parameters of a drand chain, as served at `/{hash}/info`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DrandChainInfo {
+    /// Hex-encoded compressed G2 public key of the network.
+    pub public_key: String,
+    /// Seconds between rounds.
+    pub period: u64,
+    /// Unix time of round 1.
+    pub genesis_time: u64,
+    /// Hex-encoded chain hash, which identifies the chain on relays.
+    pub hash: String,
+    #[serde(rename = "schemeID")]
+    pub scheme_id: String,
+}
+
+/// A drand beacon: the network's signature over a round number.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Beacon {
+    pub round: u64,
+    /// Hex-encoded compressed G1 signature.
+    pub signature: String,
+}
+
+/// How a time-locked entry's content key is sealed, stored in its metadata.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimeLock {
+    /// Round whose beacon decrypts the content key.
+    pub round: u64,
+    pub chain: DrandChainInfo,
+    /// IBE ciphertext of the content key.
+    pub ciphertext_b64: String,
+}
+
+impl DrandChainInfo {
+    /// The drand League of Entropy "quicknet" mainnet chain (3 s rounds).
+    pub fn quicknet() -> Self {
+        Self {
+            public_key: "83cf0f2896adee7eb8b5f01fcad3912212c437e0073e911fb90022d3e760183c8c4b450b6a0a6c3ac6a5776a2d1064510d1fec758c921cc22b0e17e63aaf4bcb5ed66304de9cf809bd274ca73bab4af5a6e9c76a4bc09e76eae8991ef5ece45a".to_string(),
+            period: 3,
+            genesis_time: 1692803367,
+            hash: "52db9ba70e0cc0f6eaf7803dd07447a1f5477735fd3f661792ba94600c84e971".to_string(),
+            scheme_id: SCHEME_ID.to_string(),
+        }
+    }
+
+    fn public_key_point(&self) -> Result<G2Affine> {
+        if self.scheme_id != SCHEME_ID {
+            return Err(anyhow!("Unsupported drand scheme {}", self.scheme_id));
+        }
+        if self.period == 0 {
+            return Err(anyhow!("Invalid drand chain period"));
+        }
+        let bytes = hex::decode(&self.public_key)?;
+        G2Affine::deserialize_compressed(bytes.as_slice()).map_err(|_| anyhow!("Invalid drand public key"))
+    }
+
+    /// Unix time at which `round` is published.
+    pub fn round_time(&self, round: u64) -> u64 {
+        self.genesis_time
+            .saturating_add(round.saturating_sub(1).saturating_mul(self.period))
+    }
+
+    /// The first round published at or after `unix_secs`.
+    pub fn round_at_or_after(&self, unix_secs: u64) -> u64 {
+        if unix_secs <= self.genesis_time || self.period == 0 {
+            return 1;
+        }
+        (unix_secs - self.genesis_time).div_ceil(self.period) + 1
+    }
+}
+
+/// `H(round)` in G1: the message drand signs and the IBE identity.
+fn round_identity(round: u64) -> Result<G1Affine> {
+    let message = Sha256::digest(round.to_be_bytes());
+    let hasher = MapToCurveBasedHasher::<G1Projective, DefaultFieldHasher<Sha256, 128>, WBMap<g1::Config>>::new(DST_G1)
+        .map_err(|_| anyhow!("hash to curve setup failed"))?;
+    hasher.hash(&message).map_err(|_| anyhow!("hash to curve failed"))
+}
+
+/// Verifies `beacon` against `chain` without contacting the network and
+/// returns its signature point.
+pub fn verify_beacon(chain: &DrandChainInfo, beacon: &Beacon) -> Result<G1Affine> {
+    let public_key = chain.public_key_point()?;
+    let bytes = hex::decode(&beacon.signature)?;
+    let signature = G1Affine::deserialize_compressed(bytes.as_slice()).map_err(|_| anyhow!("Invalid drand beacon signature"))?;
+    if signature.is_zero() {
+        return Err(anyhow!("Invalid drand beacon signature"));
+    }
+
+    let identity = round_identity(beacon.round)?;
+    if Bls12_381::pairing(signature, G2Affine::generator()) != Bls12_381::pairing(identity, public_key) {
+        return Err(anyhow!("drand beacon signature verification failed for round {}", beacon.round));
+    }
+    Ok(signature)
+}
+
+fn gt_mask(gt: &<Bls12_381 as Pairing>::TargetField) -> Result<[u8; KEY_LEN]> {
+    let mut bytes = vec![];
+    gt.serialize_compressed(&mut bytes).map_err(|_| anyhow!("pairing serialization failed"))?;
+    let mut hasher = Sha256::new();
+    hasher.update(b"IBE-H2");
+    hasher.update(&bytes);
+    bytes.zeroize();
+    Ok(hasher.finalize().into())
+}
+
+fn sigma_to_r(sigma: &[u8; KEY_LEN], key: &[u8; KEY_LEN]) -> Fr {
+    let mut hasher = Sha512::new();
+    hasher.update(b"IBE-H3");
+    hasher.update(sigma);
+    hasher.update(key);
+    Fr::from_be_bytes_mod_order(&hasher.finalize())
+}
+
+fn sigma_mask(sigma: &[u8; KEY_LEN]) -> [u8; KEY_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update(b"IBE-H4");
+    hasher.update(sigma);
+    hasher.finalize().into()
+}
+
+fn xor(a: &[u8; KEY_LEN], b: &[u8; KEY_LEN]) -> [u8; KEY_LEN] {
+    let mut out = [0u8; KEY_LEN];
+    for i in 0..KEY_LEN {
+        out[i] = a[i] ^ b[i];
+    }
+    out
+}
+
+/// Encrypts `key` so that only the beacon for `round` of `chain` can
+/// recover it (Boneh–Franklin FullIdent with the identity in G1).
+pub fn seal_key(chain: &DrandChainInfo, round: u64, key: &[u8; KEY_LEN]) -> Result<TimeLock> {
+    let public_key = chain.public_key_point()?;
+    let identity = round_identity(round)?;
+
+    let mut sigma = [0u8; KEY_LEN];
+    OsRng.fill_bytes(&mut sigma);
+    let r = sigma_to_r(&sigma, key);
+
+    let u = (G2Affine::generator() * r).into_affine();
+    let shared = Bls12_381::pairing((identity * r).into_affine(), public_key);
+    let v = xor(&sigma, &gt_mask(&shared.0)?);
+    let w = xor(key, &sigma_mask(&sigma));
+    sigma.zeroize();
+
+    let mut ciphertext = Vec::with_capacity(CIPHERTEXT_LEN);
+    u.serialize_compressed(&mut ciphertext).map_err(|_| anyhow!("point serialization failed"))?;
+    ciphertext.extend_from_slice(&v);
+    ciphertext.extend_from_slice(&w);
+
+    Ok(TimeLock {
+        round,
+        chain: chain.clone(),
+        ciphertext_b64: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+/// Recovers the key sealed in `lock` with the beacon for its round,
+/// verifying the beacon against the stored chain info first.
+pub fn open_key(lock: &TimeLock, beacon: &Beacon) -> Result<[u8; KEY_LEN]> {
+    if beacon.round != lock.round {
+        return Err(anyhow!("drand beacon is for round {}, expected {}", beacon.round, lock.round));
+    }
+    let signature = verify_beacon(&lock.chain, beacon)?;
+
+    let ciphertext = general_purpose::STANDARD.decode(&lock.ciphertext_b64)?;
+    if ciphertext.len() != CIPHERTEXT_LEN {
+        return Err(anyhow!("Invalid time-lock ciphertext length"));
+    }
+    let u = G2Affine::deserialize_compressed(&ciphertext[..G2_COMPRESSED_LEN]).map_err(|_| anyhow!("Invalid time-lock ciphertext"))?;
+    let mut v = [0u8; KEY_LEN];
+    let mut w = [0u8; KEY_LEN];
+    v.copy_from_slice(&ciphertext[G2_COMPRESSED_LEN..G2_COMPRESSED_LEN + KEY_LEN]);
+    w.copy_from_slice(&ciphertext[G2_COMPRESSED_LEN + KEY_LEN..]);
+
+    let shared = Bls12_381::pairing(signature, u);
+    let mut sigma = xor(&v, &gt_mask(&shared.0)?);
+    let key = xor(&w, &sigma_mask(&sigma));
+
+    // FullIdent's consistency check: U must be r·G2 for the r derived from
+    // the recovered sigma and key, otherwise the ciphertext was altered.
+    let r = sigma_to_r(&sigma, &key);
+    sigma.zeroize();
+    if (G2Affine::generator() * r).into_affine() != u {
+        return Err(VaultError::tampered("Time-lock ciphertext integrity check failed").into());
+    }
+    Ok(key)
+}
+
+/// Public HTTP relays serving drand beacons.
+pub const DEFAULT_RELAYS: &[&str] = &["https://api.drand.sh", "https://api2.drand.sh", "https://drand.cloudflare.com"];
+
+/// Fetches and verifies the beacon for `round` of `chain` from the public
+/// relays. Fails with [`VaultError::StillLocked`] if the round has not been
+/// published yet.
+pub async fn fetch_beacon(chain: &DrandChainInfo, round: u64) -> Result<Beacon> {
+    let client = Client::builder().user_agent("vault-client/1.0").build()?;
+    let mut not_yet = false;
+
+    for relay in DEFAULT_RELAYS {
+        let url = format!("{}/{}/public/{}", relay, chain.hash, round);
+        let Ok(resp) = client.get(&url).send().await else {
+            continue;
+        };
+        if resp.status().as_u16() == TOO_EARLY || resp.status() == StatusCode::NOT_FOUND {
+            not_yet = true;
+            continue;
+        }
+        let Ok(beacon) = resp.json::<Beacon>().await else {
+            continue;
+        };
+        if beacon.round == round && verify_beacon(chain, &beacon).is_ok() {
+            return Ok(beacon);
+        }
+    }
+
+    if not_yet {
+        Err(VaultError::StillLocked {
+            unlock_at: chain.round_time(round),
+        }
+        .into())
+    } else {
+        Err(anyhow!("Could not fetch a valid drand beacon for round {}", round))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Quicknet round 1000 as published by the League of Entropy.
+    fn quicknet_round_1000() -> Beacon {
+        Beacon {
+            round: 1000,
+            signature: "b44679b9a59af2ec876b1a6b1ad52ea9b1615fc3982b19576350f93447cb1125e342b73a8dd2bacbe47e4b6b63ed5e39".to_string(),
+        }
+    }
+
+    fn hex_point(point: impl CanonicalSerialize) -> String {
+        let mut bytes = vec![];
+        point.serialize_compressed(&mut bytes).unwrap();
+        hex::encode(bytes)
+    }
+
+    /// A chain run by a locally generated key pair, and a signer for its
+    /// rounds.
+    fn local_chain() -> (DrandChainInfo, impl Fn(u64) -> Beacon) {
+        let secret = Fr::from(0x7469_6d65_7661_756cu64);
+        let chain = DrandChainInfo {
+            public_key: hex_point((G2Affine::generator() * secret).into_affine()),
+            hash: "00".repeat(32),
+            ..DrandChainInfo::quicknet()
+        };
+        let sign = move |round| Beacon {
+            round,
+            signature: hex_point((round_identity(round).unwrap() * secret).into_affine()),
+        };
+        (chain, sign)
+    }
+
+    #[test]
+    fn verifies_a_published_quicknet_beacon() {
+        let chain = DrandChainInfo::quicknet();
+        let beacon = quicknet_round_1000();
+        let signature = verify_beacon(&chain, &beacon).unwrap();
+        assert_eq!(hex_point(signature), beacon.signature);
+        assert_eq!(chain.round_time(1000), 1692803367 + 999 * 3);
+        assert_eq!(chain.round_at_or_after(chain.round_time(1000)), 1000);
+    }
+
+    #[test]
+    fn rejects_a_beacon_for_another_round() {
+        let chain = DrandChainInfo::quicknet();
+        let beacon = Beacon {
+            round: 1001,
+            ..quicknet_round_1000()
+        };
+        let err = verify_beacon(&chain, &beacon).unwrap_err();
+        assert!(err.to_string().contains("verification failed for round 1001"), "{}", err);
+
+        let (local, sign) = local_chain();
+        assert!(verify_beacon(&local, &quicknet_round_1000()).is_err());
+        assert!(verify_beacon(&chain, &sign(1000)).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_beacons() {
+        let chain = DrandChainInfo::quicknet();
+        for signature in ["", "zz", &"00".repeat(48), &format!("c0{}", "00".repeat(47))] {
+            let beacon = Beacon {
+                round: 1000,
+                signature: signature.to_string(),
+            };
+            assert!(verify_beacon(&chain, &beacon).is_err(), "{}", signature);
+        }
+    }
+
+    #[test]
+    fn opens_with_the_published_quicknet_beacon() {
+        let key = [0x42; KEY_LEN];
+        let lock = seal_key(&DrandChainInfo::quicknet(), 1000, &key).unwrap();
+        assert_eq!(open_key(&lock, &quicknet_round_1000()).unwrap(), key);
+    }
+
+    #[test]
+    fn round_trips_with_a_local_chain() {
+        let (chain, sign) = local_chain();
+        let key = [0x24; KEY_LEN];
+        let lock = seal_key(&chain, 77, &key).unwrap();
+        assert_eq!(open_key(&lock, &sign(77)).unwrap(), key);
+
+        let err = open_key(&lock, &sign(78)).unwrap_err();
+        assert!(err.to_string().contains("round 78, expected 77"), "{}", err);
+        let forged = Beacon {
+            round: 77,
+            ..sign(78)
+        };
+        assert!(open_key(&lock, &forged).is_err());
+    }
+
+    #[test]
+    fn detects_an_altered_ciphertext() {
+        let (chain, sign) = local_chain();
+        let lock = seal_key(&chain, 5, &[0x24; KEY_LEN]).unwrap();
+        let ciphertext = general_purpose::STANDARD.decode(&lock.ciphertext_b64).unwrap();
+
+        for i in [G2_COMPRESSED_LEN, G2_COMPRESSED_LEN + KEY_LEN, CIPHERTEXT_LEN - 1] {
+            let mut altered = ciphertext.clone();
+            altered[i] ^= 1;
+            let lock = TimeLock {
+                ciphertext_b64: general_purpose::STANDARD.encode(altered),
+                ..lock.clone()
+            };
+            let err = VaultError::from(open_key(&lock, &sign(5)).unwrap_err());
+            assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+        }
+
+        let truncated = TimeLock {
+            ciphertext_b64: general_purpose::STANDARD.encode(&ciphertext[1..]),
+            ..lock
+        };
+        assert!(open_key(&truncated, &sign(5)).is_err());
+    }
+
+    #[test]
+    fn refuses_other_schemes() {
+        let chain = DrandChainInfo {
+            scheme_id: "pedersen-bls-chained".to_string(),
+            ..DrandChainInfo::quicknet()
+        };
+        assert!(seal_key(&chain, 1000, &[0; KEY_LEN]).is_err());
+        assert!(verify_beacon(&chain, &quicknet_round_1000()).is_err());
+    }
+}
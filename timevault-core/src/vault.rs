@@ -0,0 +1,1109 @@
+//! Vault operations: creation, adding entries, listing and time-gated unlocking.
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroize;
+
+use crate::crypto::{
+    calibrate_argon_params, decrypt_file_metadata, decrypt_file_metadata_bytes, default_argon_params, derive_key, encrypt_file_metadata,
+    seal_vault_state, sha256_hex, unwrap_data_key, unwrap_fek, verify_vault_state, wrap_data_key, wrap_fek,
+};
+use crate::metadata::{
+    blob_path, blobs_dir, deletion_log_path, ensure_vault_dir, entry_meta_path, files_meta_dir, is_entry_meta_file, new_blob_id,
+    puzzle_progress_path, read_deletion_log, read_vault_metadata, rekey_journal_path, sync_dir, vault_meta_path, write_json_atomic,
+    write_vault_metadata,
+    ArgonCalibration, DeletePolicy, DeletionRecord, EncryptedFileMeta, FileMetaPayload, VaultInfo, VaultMetadata, VAULT_VERSION,
+};
+use crate::error::VaultError;
+use crate::journal::{self, PendingOp};
+use crate::lock::VaultLock;
+use crate::migrate::upgrade_vault;
+use crate::name::EntryName;
+use crate::session::{open_vault, VaultSession};
+use crate::stream::{decrypt_stream, encrypt_stream, StreamSummary, DEFAULT_CHUNK_SIZE};
+use crate::time::TimeVerifier;
+use crate::puzzle::{calibrate, seal_key as seal_puzzle, PuzzleProgress};
+use crate::tlock::{fetch_beacon, open_key, seal_key, DrandChainInfo};
+
+/// Reads the vault header, unwraps the FEK with `password`, upgrades older
+/// vaults, verifies the header's state MAC and recovers any add or delete
+/// that was cut short.
+///
+/// Refuses to open a vault whose key rotation was interrupted, since its
+/// entries are then split between the old and the new key.
+pub(crate) fn open_vault_key(vault_path: &Path, password: &str) -> Result<(VaultMetadata, [u8; 32])> {
+    if rekey_journal_path(vault_path).exists() {
+        return Err(VaultError::RekeyPending.into());
+    }
+    open_vault_key_during_rekey(vault_path, password)
+}
+
+/// [`open_vault_key`] without the interrupted-rotation check, for use by the
+/// rotation itself.
+pub(crate) fn open_vault_key_during_rekey(vault_path: &Path, password: &str) -> Result<(VaultMetadata, [u8; 32])> {
+    let mut meta = read_vault_metadata(vault_path)?;
+    let mut fek_arr = unwrap_fek(&meta, password)?;
+    let checked = upgrade_vault(vault_path, &mut meta, &fek_arr, password)
+        .and_then(|_| verify_vault_state(&fek_arr, &meta))
+        .and_then(|_| recover_pending_op(vault_path, &fek_arr, &meta));
+    if let Err(e) = checked {
+        fek_arr.zeroize();
+        return Err(e);
+    }
+    Ok((meta, fek_arr))
+}
+
+/// Completes or undoes an add or delete left in progress (see
+/// [`journal`](crate::journal)).
+///
+/// A delete is only rolled forward if the entry's own metadata names the
+/// journaled blob and `meta`'s delete policy allows removing it; otherwise
+/// the journal is simply cleared, so a forged journal cannot delete an entry
+/// that is still protected.
+pub(crate) fn recover_pending_op(vault_path: &Path, fek: &[u8; 32], meta: &VaultMetadata) -> Result<()> {
+    match journal::pending(vault_path)? {
+        None => return Ok(()),
+        Some(PendingOp::Add { blob_id }) => {
+            if !entry_meta_path(vault_path, &blob_id).exists() {
+                let blob = blobs_dir(vault_path).join(&blob_id);
+                remove_if_exists(&blob.with_extension("tmp"))?;
+                remove_if_exists(&blob)?;
+            }
+        }
+        Some(PendingOp::Delete { blob_id }) => {
+            let meta_path = entry_meta_path(vault_path, &blob_id);
+            if meta_path.exists() {
+                let payload = serde_json::from_slice::<EncryptedFileMeta>(&fs::read(&meta_path)?).ok().and_then(|encrypted_meta| {
+                    decrypt_file_metadata(fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64).ok()
+                });
+                if let Some(payload) = payload.filter(|payload| payload.blob_id == blob_id && !delete_needs_time(meta, payload)) {
+                    remove_entry(vault_path, fek, &payload)?;
+                }
+            } else {
+                remove_entry_data(vault_path, &blob_id)?;
+            }
+        }
+    }
+    journal::commit(vault_path)
+}
+
+pub(crate) fn remove_if_exists(path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Decrypts an entry's blob with its content `key` to `out_file`, checking
+/// the plaintext against the size and hash recorded in its metadata.
+///
+/// Plaintext is written to a temporary file next to `out_file` and only
+/// renamed into place once it has fully authenticated.
+fn decrypt_entry(vault_path: &Path, key: &[u8; 32], payload: &FileMetaPayload, out_file: &Path) -> Result<()> {
+    let file_name = out_file.file_name().ok_or_else(|| anyhow!("bad filename"))?;
+    let partial = out_file.with_file_name(format!(".{}.partial", file_name.to_string_lossy()));
+
+    let result = decrypt_entry_to(vault_path, key, payload, &partial);
+    match result {
+        Ok((size, sha256)) if size == payload.file_size && sha256 == payload.content_sha256 => {
+            fs::rename(&partial, out_file)?;
+            Ok(())
+        }
+        Ok(_) => {
+            let _ = fs::remove_file(&partial);
+            Err(VaultError::Tampered {
+                entry: Some(payload.filename.clone()),
+                reason: "Content hash mismatch".to_string(),
+            }
+            .into())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+fn decrypt_entry_to(vault_path: &Path, key: &[u8; 32], payload: &FileMetaPayload, dest: &Path) -> Result<(u64, String)> {
+    let nonce_bytes = general_purpose::STANDARD.decode(&payload.nonce_b64)?;
+    let blob = File::open(blob_path(vault_path, payload))?;
+    let out = BufWriter::new(File::create(dest)?);
+
+    match payload.chunk_size {
+        Some(chunk_size) => decrypt_stream(key, &nonce_bytes, chunk_size, BufReader::new(blob), out),
+        None => {
+            if nonce_bytes.len() != 24 {
+                return Err(anyhow!("Invalid nonce length"));
+            }
+            let mut ciphertext = vec![];
+            BufReader::new(blob).read_to_end(&mut ciphertext)?;
+            let mut plaintext = XChaCha20Poly1305::new(Key::from_slice(key))
+                .decrypt(XNonce::from_slice(&nonce_bytes), ciphertext.as_ref())
+                .map_err(|_| VaultError::tampered("Decryption failed"))?;
+            let mut out = out;
+            out.write_all(&plaintext)?;
+            out.flush()?;
+            let summary = (plaintext.len() as u64, sha256_hex(&plaintext));
+            plaintext.zeroize();
+            Ok(summary)
+        }
+    }
+}
+
+/// Returns the key an entry's content is encrypted with: its data key
+/// unwrapped with the FEK, the content key recovered with the drand beacon
+/// for its round, the content key recovered from its solved time-lock
+/// puzzle, or the FEK itself for entries written before per-entry keys.
+async fn entry_key(vault_path: &Path, fek: &[u8; 32], payload: &FileMetaPayload) -> Result<[u8; 32]> {
+    if let Some(puzzle) = &payload.puzzle {
+        let progress = read_puzzle_progress(vault_path, payload)?.ok_or(VaultError::PuzzleUnsolved {
+            done: 0,
+            total: puzzle.squarings,
+        })?;
+        return puzzle.open_key(&progress);
+    }
+    if let Some(lock) = &payload.time_lock {
+        let beacon = fetch_beacon(&lock.chain, lock.round).await?;
+        return open_key(lock, &beacon);
+    }
+    match &payload.data_key {
+        Some(wrapped) => unwrap_data_key(fek, &payload.blob_id, wrapped),
+        None => Ok(*fek),
+    }
+}
+
+/// Recovers an entry's content key and decrypts it into `out_path`.
+///
+/// The stored name is validated again before it is joined onto `out_path`,
+/// since the metadata it came from may have been crafted to escape it.
+async fn unlock_entry(vault_path: &Path, fek: &[u8; 32], payload: &FileMetaPayload, out_path: &Path) -> Result<()> {
+    let name = EntryName::new(&payload.filename).map_err(|e| VaultError::Tampered {
+        entry: Some(payload.filename.clone()),
+        reason: e.to_string(),
+    })?;
+    let mut key = entry_key(vault_path, fek, payload).await?;
+    let result = decrypt_entry(vault_path, &key, payload, &out_path.join(&name));
+    key.zeroize();
+    result
+}
+
+/// Finds the entry named `filename`, skipping records that fail to decrypt.
+/// Reads the metadata of the entry stored as `blob_id`, if it still exists.
+fn read_entry(vault_path: &Path, fek: &[u8; 32], blob_id: &str) -> Result<Option<FileMetaPayload>> {
+    let path = entry_meta_path(vault_path, blob_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let encrypted_meta: EncryptedFileMeta = serde_json::from_slice(&fs::read(&path)?)?;
+    Ok(Some(decrypt_file_metadata(fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64)?))
+}
+
+fn find_entry(vault_path: &Path, fek: &[u8; 32], filename: &str) -> Result<Option<FileMetaPayload>> {
+    let fm_dir = files_meta_dir(vault_path);
+    if !fm_dir.exists() {
+        return Ok(None);
+    }
+    for entry in fs::read_dir(&fm_dir)? {
+        let path = entry?.path();
+        if is_entry_meta_file(&path) {
+            let raw = fs::read(&path)?;
+            if let Ok(encrypted_meta) = serde_json::from_slice::<EncryptedFileMeta>(&raw) {
+                if let Ok(payload) = decrypt_file_metadata(fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64) {
+                    if payload.filename == filename {
+                        return Ok(Some(payload));
+                    }
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the latest unlock date of any entry, or 0 if the vault is empty,
+/// skipping records that fail to decrypt.
+fn latest_unlock_date(vault_path: &Path, fek: &[u8; 32]) -> Result<u64> {
+    let fm_dir = files_meta_dir(vault_path);
+    if !fm_dir.exists() {
+        return Ok(0);
+    }
+    let mut latest = 0;
+    for entry in fs::read_dir(&fm_dir)? {
+        let path = entry?.path();
+        if is_entry_meta_file(&path) {
+            let raw = fs::read(&path)?;
+            if let Ok(encrypted_meta) = serde_json::from_slice::<EncryptedFileMeta>(&raw) {
+                if let Ok(payload) = decrypt_file_metadata(fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64) {
+                    latest = latest.max(payload.file_unlock_date);
+                }
+            }
+        }
+    }
+    Ok(latest)
+}
+
+/// Confirms the current time with `verifier`, refusing a time before the
+/// vault's watermark, and records it as the authenticated time.
+async fn record_verified_time(vault_path: &Path, fek: &[u8; 32], meta: &mut VaultMetadata, verifier: &TimeVerifier) -> Result<u64> {
+    let (server_time, _) = verifier.verify().await?;
+    let watermark = meta.time_watermark();
+    if watermark != 0 && server_time < watermark {
+        return Err(VaultError::TimeRegression.into());
+    }
+    meta.last_verified_time = server_time;
+    meta.authenticated_time = server_time;
+    seal_vault_state(fek, meta)?;
+    write_vault_metadata(vault_path, meta)?;
+    Ok(server_time)
+}
+
+fn read_puzzle_progress(vault_path: &Path, payload: &FileMetaPayload) -> Result<Option<PuzzleProgress>> {
+    let path = puzzle_progress_path(vault_path, &payload.blob_id);
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&fs::read(path)?)?))
+}
+
+fn write_puzzle_progress(vault_path: &Path, payload: &FileMetaPayload, progress: &PuzzleProgress) -> Result<()> {
+    let path = puzzle_progress_path(vault_path, &payload.blob_id);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_json_atomic(&path, progress)
+}
+
+/// Creates a new vault in `vault_dir` protected by `password`.
+///
+/// Generates a random file encryption key (FEK), wraps it under a key derived
+/// from the password with Argon2id and writes `vault_metadata.json`. Fails
+/// with [`VaultError::VaultExists`] rather than overwriting an existing
+/// vault, whose entries would be lost with its key.
+pub fn init_vault(vault_dir: String, password: String) -> Result<()> {
+    let vault_path = Path::new(&vault_dir);
+    ensure_vault_dir(vault_path)?;
+    let _lock = VaultLock::acquire_new(vault_path)?;
+    if vault_meta_path(vault_path).exists() {
+        return Err(VaultError::VaultExists.into());
+    }
+
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+
+    let (mem_kib, iters, parallelism) = default_argon_params();
+
+    let mut derived = derive_key(&password, &salt, mem_kib, iters, parallelism)?;
+    let mut fek = [0u8; 32];
+    OsRng.fill_bytes(&mut fek);
+
+    let (wrapped_fek_b64, wrap_nonce_b64) = wrap_fek(&derived, &fek)?;
+
+    let mut meta = VaultMetadata {
+        version: VAULT_VERSION,
+        salt_b64: general_purpose::STANDARD.encode(salt),
+        argon_mem_kib: mem_kib,
+        argon_iters: iters,
+        argon_parallelism: parallelism,
+        wrapped_fek_b64,
+        wrap_nonce_b64,
+        creation_ts: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        last_verified_time: 0,
+        authenticated_time: 0,
+        state_mac_b64: String::new(),
+        argon_calibration: None,
+        delete_policy: DeletePolicy::Any,
+    };
+    seal_vault_state(&fek, &mut meta)?;
+
+    write_vault_metadata(vault_path, &meta)?;
+    for dir in [files_meta_dir(vault_path), blobs_dir(vault_path)] {
+        if !dir.exists() {
+            fs::create_dir_all(&dir)?;
+        }
+    }
+
+    derived.zeroize();
+    fek.zeroize();
+    salt.zeroize();
+
+    Ok(())
+}
+
+/// Encrypts `file_path` into the vault, locked until `file_unlock_date`
+/// (Unix seconds).
+///
+/// The entry is stored under `custom_filename` if given, otherwise under the
+/// source file's name; either must be a valid [`EntryName`]. Fails with
+/// [`VaultError::FileExists`] if an entry with that name already exists.
+pub fn add_file_with_name(vault_dir: String, file_path: String, password: String, file_unlock_date: u64, custom_filename: Option<String>) -> Result<()> {
+    open_vault(vault_dir, password)?.add_file(&file_path, file_unlock_date, custom_filename.as_deref())
+}
+
+/// Like [`add_file_with_name`], but also time-lock encrypts the entry's
+/// content key to the first round of the drand quicknet chain published at
+/// or after `file_unlock_date`, so the content cannot be decrypted before
+/// then even by someone holding the password and controlling the clock.
+pub fn add_file_time_locked(vault_dir: String, file_path: String, password: String, file_unlock_date: u64, custom_filename: Option<String>) -> Result<()> {
+    open_vault(vault_dir, password)?.add_file_time_locked(&file_path, file_unlock_date, custom_filename.as_deref())
+}
+
+/// Like [`add_file_with_name`], but wraps the entry's content key in a
+/// time-lock puzzle calibrated to this machine so that solving it with
+/// [`solve_puzzle`] takes about as long as the time remaining until
+/// `file_unlock_date`. Such entries open without any network access.
+pub fn add_file_puzzle_locked(vault_dir: String, file_path: String, password: String, file_unlock_date: u64, custom_filename: Option<String>) -> Result<()> {
+    let delay_secs = puzzle_delay(file_unlock_date)?;
+    open_vault(vault_dir, password)?.add_entry(&file_path, file_unlock_date, custom_filename.as_deref(), EntryLock::Puzzle(delay_secs))
+}
+
+fn puzzle_delay(file_unlock_date: u64) -> Result<u64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    if file_unlock_date <= now {
+        return Err(anyhow!("Unlock date must be in the future for a time-lock puzzle"));
+    }
+    Ok(file_unlock_date - now)
+}
+
+/// What keeps an entry closed until its unlock date.
+enum EntryLock<'a> {
+    /// Only the verified-time policy; the content key is wrapped by the FEK.
+    Policy,
+    /// The content key is time-lock encrypted to a drand round.
+    Drand(&'a DrandChainInfo),
+    /// The content key is wrapped in a puzzle taking this many seconds.
+    Puzzle(u64),
+}
+
+/// Encrypts the file at `source` under `key` into `blob`, through a synced
+/// temporary file that is renamed into place.
+fn write_blob(blob: &Path, key: &[u8; 32], source: &Path) -> Result<StreamSummary> {
+    let tmp = blob.with_extension("tmp");
+    let out = File::create(&tmp)?;
+    let summary = encrypt_stream(key, DEFAULT_CHUNK_SIZE, BufReader::new(File::open(source)?), BufWriter::new(&out))?;
+    out.sync_all()?;
+    fs::rename(&tmp, blob)?;
+    if let Some(dir) = blob.parent() {
+        sync_dir(dir)?;
+    }
+    Ok(summary)
+}
+
+/// Encrypts `file_path` into the vault under its own file name.
+pub fn add_file(vault_dir: String, file_path: String, password: String, file_unlock_date: u64) -> Result<()> {
+    add_file_with_name(vault_dir, file_path, password, file_unlock_date, None)
+}
+
+/// Decrypts every entry whose unlock date has passed into `out_dir`.
+///
+/// The current time must be confirmed by [`TimeVerifier::default`]; the call
+/// fails if too few time sources agree or they report a time earlier than
+/// the vault's last verified time. Entries whose time-lock puzzle has been
+/// solved are decrypted regardless, and the call only fails for lack of a
+/// verified time if there were none.
+pub async fn unlock_vault(vault_dir: String, out_dir: String, password: String) -> Result<String> {
+    unlock_vault_with_verifier(vault_dir, out_dir, password, &TimeVerifier::default()).await
+}
+
+/// [`unlock_vault`] with the current time confirmed by `verifier`.
+pub async fn unlock_vault_with_verifier(vault_dir: String, out_dir: String, password: String, verifier: &TimeVerifier) -> Result<String> {
+    open_vault(vault_dir, password)?.unlock_vault(&out_dir, verifier).await
+}
+
+/// Decrypts the single entry named `filename` into `out_dir`.
+///
+/// Fails with [`VaultError::StillLocked`] if the verified public
+/// time is before the entry's unlock date. Entries locked with a time-lock
+/// puzzle need no verified time, only a solved puzzle (see [`solve_puzzle`]).
+pub async fn unlock_file(vault_dir: String, out_dir: String, password: String, filename: String) -> Result<String> {
+    unlock_file_with_verifier(vault_dir, out_dir, password, filename, &TimeVerifier::default()).await
+}
+
+/// [`unlock_file`] with the current time confirmed by `verifier`.
+pub async fn unlock_file_with_verifier(vault_dir: String, out_dir: String, password: String, filename: String, verifier: &TimeVerifier) -> Result<String> {
+    open_vault(vault_dir, password)?.unlock_file(&out_dir, &filename, verifier).await
+}
+
+/// Works on the time-lock puzzle of the entry named `filename`, resuming
+/// from saved progress.
+///
+/// Progress is saved about every `checkpoint_secs` seconds of work, after
+/// which `on_checkpoint(done, total)` is called; returning `false` from it
+/// stops the solver. A checkpoint that finds the vault busy is skipped and
+/// its work saved at the next one; deleting or replacing the entry meanwhile
+/// stops the solver with [`VaultError::FileNotFound`]. Returns `true` once
+/// the puzzle is solved, after which [`unlock_file`] opens the entry without
+/// network access.
+pub fn solve_puzzle(vault_dir: String, password: String, filename: String, checkpoint_secs: u64, on_checkpoint: impl FnMut(u64, u64) -> bool) -> Result<bool> {
+    open_vault(vault_dir, password)?.solve_puzzle(&filename, checkpoint_secs, on_checkpoint)
+}
+
+/// Moves the unlock date of the entry named `filename` to `new_unlock_date`.
+///
+/// Dates can only be pushed back: any earlier date is refused with
+/// [`VaultError::UnlockDateEarlier`], so an entry never opens sooner than it
+/// was committed to. Only the entry's encrypted metadata is rewritten. For a
+/// drand time-locked entry the content key stays sealed to its original
+/// round, so the later date is enforced by the time policy on top of it.
+/// Puzzle-locked entries open as soon as their puzzle is solved and cannot be
+/// extended.
+///
+/// The extension binds only as long as the rewritten record is kept: the
+/// sealed vault state does not record it, so putting back the entry's
+/// earlier `files_meta` record from a backup restores the earlier date.
+pub fn extend_unlock_date(vault_dir: String, password: String, filename: String, new_unlock_date: u64) -> Result<()> {
+    open_vault(vault_dir, password)?.extend_unlock_date(&filename, new_unlock_date)
+}
+
+/// Renames the entry `filename` to `new_filename`.
+///
+/// Only the entry's encrypted metadata is rewritten; its blob id and content
+/// ciphertext are unchanged. `new_filename` must be a valid [`EntryName`].
+/// Fails with [`VaultError::FileExists`] if another entry already has that
+/// name.
+pub fn rename_entry(vault_dir: String, password: String, filename: String, new_filename: String) -> Result<()> {
+    open_vault(vault_dir, password)?.rename_entry(&filename, &new_filename)
+}
+
+/// Encrypts `payload` and atomically writes the entry's metadata record.
+fn write_entry_metadata(vault_path: &Path, fek: &[u8; 32], payload: &FileMetaPayload) -> Result<()> {
+    let (encrypted_payload_b64, metadata_nonce_b64) = encrypt_file_metadata(fek, payload)?;
+    let file_meta = EncryptedFileMeta {
+        encrypted_payload_b64,
+        metadata_nonce_b64,
+    };
+    write_json_atomic(&entry_meta_path(vault_path, &payload.blob_id), &file_meta)
+}
+
+/// Removes the entry named `filename` from the vault, recording the deletion
+/// in the vault's encrypted deletion log (see [`deletion_log`]).
+///
+/// If the vault's [`DeletePolicy`] is `UnlockedOnly`, entries whose unlock
+/// date is after the authenticated time watermark can only be deleted once
+/// verified public time confirms the date has passed.
+pub async fn delete_entry(vault_dir: String, password: String, filename: String) -> Result<()> {
+    delete_entry_with_verifier(vault_dir, password, filename, &TimeVerifier::default()).await
+}
+
+/// [`delete_entry`] with the current time confirmed by `verifier`.
+pub async fn delete_entry_with_verifier(vault_dir: String, password: String, filename: String, verifier: &TimeVerifier) -> Result<()> {
+    open_vault(vault_dir, password)?.delete_entry(&filename, verifier).await
+}
+
+/// Whether deleting the entry described by `payload` needs a verified time
+/// first: under [`DeletePolicy::UnlockedOnly`], an entry that was not yet due
+/// at the last verified time is still protected.
+fn delete_needs_time(meta: &VaultMetadata, payload: &FileMetaPayload) -> bool {
+    meta.delete_policy == DeletePolicy::UnlockedOnly && payload.file_unlock_date > meta.authenticated_time
+}
+
+/// Records the deletion of the entry described by `payload` and removes its
+/// files. Safe to repeat.
+///
+/// The deletion is recorded first, and removing the metadata record is what
+/// takes the entry out of the vault.
+fn remove_entry(vault_path: &Path, fek: &[u8; 32], payload: &FileMetaPayload) -> Result<()> {
+    record_deletion(vault_path, fek, payload)?;
+    remove_if_exists(&entry_meta_path(vault_path, &payload.blob_id))?;
+    sync_dir(&files_meta_dir(vault_path))?;
+    remove_entry_data(vault_path, &payload.blob_id)
+}
+
+/// Removes the blob and any puzzle progress of the entry stored as `blob_id`.
+fn remove_entry_data(vault_path: &Path, blob_id: &str) -> Result<()> {
+    remove_if_exists(&blobs_dir(vault_path).join(blob_id))?;
+    remove_if_exists(&puzzle_progress_path(vault_path, blob_id))?;
+    sync_dir(&blobs_dir(vault_path))
+}
+
+fn record_deletion(vault_path: &Path, fek: &[u8; 32], payload: &FileMetaPayload) -> Result<()> {
+    let mut log = read_deletion_log(vault_path)?;
+    let already_recorded = log.iter().any(|record| {
+        decrypt_file_metadata_bytes(fek, &record.encrypted_payload_b64, &record.metadata_nonce_b64)
+            .ok()
+            .and_then(|raw| serde_json::from_slice::<DeletionRecord>(&raw).ok())
+            .is_some_and(|record| record.blob_id == payload.blob_id)
+    });
+    if already_recorded {
+        return Ok(());
+    }
+
+    let record = DeletionRecord {
+        filename: payload.filename.clone(),
+        blob_id: payload.blob_id.clone(),
+        file_unlock_date: payload.file_unlock_date,
+        content_sha256: payload.content_sha256.clone(),
+        deleted_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    };
+    let (encrypted_payload_b64, metadata_nonce_b64) = encrypt_file_metadata(fek, &record)?;
+    log.push(EncryptedFileMeta {
+        encrypted_payload_b64,
+        metadata_nonce_b64,
+    });
+    write_json_atomic(&deletion_log_path(vault_path), &log)
+}
+
+/// Returns the entries deleted from the vault, oldest first.
+pub fn deletion_log(vault_dir: String, password: String) -> Result<Vec<DeletionRecord>> {
+    open_vault(vault_dir, password)?.deletion_log()
+}
+
+/// Sets which entries [`delete_entry`] may remove. The policy is covered by
+/// the header's state MAC.
+///
+/// `UnlockedOnly` is a commitment: switching back to `Any` fails with
+/// [`VaultError::PolicyLocked`] until verified public time has passed the
+/// unlock date of every entry.
+pub async fn set_delete_policy(vault_dir: String, password: String, policy: DeletePolicy) -> Result<()> {
+    set_delete_policy_with_verifier(vault_dir, password, policy, &TimeVerifier::default()).await
+}
+
+/// [`set_delete_policy`] with the current time confirmed by `verifier`.
+pub async fn set_delete_policy_with_verifier(vault_dir: String, password: String, policy: DeletePolicy, verifier: &TimeVerifier) -> Result<()> {
+    open_vault(vault_dir, password)?.set_delete_policy(policy, verifier).await
+}
+
+/// Lists the vault's entries as JSON objects.
+///
+/// Entries whose metadata fails to parse or authenticate are reported in a
+/// trailing `{"_tampering_warnings": [...]}` element.
+pub fn get_status_with_password(vault_path: String, password: String) -> Result<Vec<serde_json::Value>> {
+    open_vault(vault_path, password)?.status()
+}
+
+/// The vault operations, run with the key held by an open session. The
+/// password-taking functions above open a session for a single call; see
+/// them for what each operation does.
+impl VaultSession {
+    /// [`add_file_with_name`] with this session's key.
+    pub fn add_file(&self, file_path: &str, file_unlock_date: u64, custom_filename: Option<&str>) -> Result<()> {
+        self.add_entry(file_path, file_unlock_date, custom_filename, EntryLock::Policy)
+    }
+
+    /// [`add_file_time_locked`] with this session's key.
+    pub fn add_file_time_locked(&self, file_path: &str, file_unlock_date: u64, custom_filename: Option<&str>) -> Result<()> {
+        self.add_entry(file_path, file_unlock_date, custom_filename, EntryLock::Drand(&DrandChainInfo::quicknet()))
+    }
+
+    /// [`add_file_puzzle_locked`] with this session's key.
+    pub fn add_file_puzzle_locked(&self, file_path: &str, file_unlock_date: u64, custom_filename: Option<&str>) -> Result<()> {
+        self.add_entry(file_path, file_unlock_date, custom_filename, EntryLock::Puzzle(puzzle_delay(file_unlock_date)?))
+    }
+
+    fn add_entry(&self, file_path: &str, file_unlock_date: u64, custom_filename: Option<&str>, lock: EntryLock) -> Result<()> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        let file = Path::new(file_path);
+        let fname = if let Some(custom_name) = custom_filename {
+            EntryName::new(custom_name)?
+        } else {
+            EntryName::new(&file.file_name().ok_or_else(|| anyhow!("bad filename"))?.to_string_lossy())?
+        };
+
+        // Sealing to a drand round or in a puzzle takes seconds, so it is
+        // done before the vault is locked.
+        let blob_id = new_blob_id();
+        let mut content_key = [0u8; 32];
+        OsRng.fill_bytes(&mut content_key);
+        let mut data_key = None;
+        let mut time_lock = None;
+        let mut puzzle = None;
+        let sealed = match lock {
+            EntryLock::Policy => wrap_data_key(fek, &blob_id, &content_key).map(|k| data_key = Some(k)),
+            EntryLock::Drand(chain) => seal_key(chain, chain.round_at_or_after(file_unlock_date), &content_key).map(|l| time_lock = Some(l)),
+            EntryLock::Puzzle(delay_secs) => seal_puzzle(&content_key, delay_secs, calibrate()).map(|p| puzzle = Some(p)),
+        };
+
+        let result = sealed.and_then(|_| {
+            let (_lock, meta) = self.begin()?;
+            if find_entry(vault_path, fek, fname.as_str())?.is_some() {
+                return Err(VaultError::FileExists { name: fname.into() }.into());
+            }
+
+            fs::create_dir_all(blobs_dir(vault_path))?;
+            let blob = blobs_dir(vault_path).join(&blob_id);
+            let result = journal::begin(vault_path, &PendingOp::Add { blob_id: blob_id.clone() })
+                .and_then(|_| write_blob(&blob, &content_key, file))
+                .and_then(|summary| {
+                    let payload = FileMetaPayload {
+                        filename: fname.into(),
+                        file_size: summary.size,
+                        file_unlock_date,
+                        content_sha256: summary.sha256,
+                        blob_id: blob_id.clone(),
+                        nonce_b64: general_purpose::STANDARD.encode(summary.nonce_prefix),
+                        chunk_size: Some(DEFAULT_CHUNK_SIZE),
+                        data_key,
+                        time_lock,
+                        puzzle,
+                    };
+                    write_entry_metadata(vault_path, fek, &payload)
+                })
+                .and_then(|_| journal::commit(vault_path));
+            if result.is_err() {
+                let _ = recover_pending_op(vault_path, fek, &meta);
+            }
+            result
+        });
+        content_key.zeroize();
+        result
+    }
+
+    /// [`unlock_vault_with_verifier`] with this session's key.
+    pub async fn unlock_vault(&self, out_dir: &str, verifier: &TimeVerifier) -> Result<String> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        let out_path = Path::new(out_dir);
+        let (_lock, mut meta) = self.begin()?;
+
+        // Puzzle-locked entries do not need the current time, so a failure to
+        // verify it only matters if nothing else could be unlocked.
+        let server_time = verifier.verify().await.map(|(time, _)| time);
+        if let Ok(server_time) = server_time {
+            let watermark = meta.time_watermark();
+            if watermark != 0 && server_time < watermark {
+                return Err(VaultError::TimeRegression.into());
+            }
+        }
+
+        fs::create_dir_all(out_path)?;
+
+        let fm_dir = files_meta_dir(vault_path);
+        let mut decrypted_files = vec![];
+
+        if fm_dir.exists() {
+            for entry in fs::read_dir(fm_dir)? {
+                let path = entry?.path();
+                if is_entry_meta_file(&path) {
+                    let raw = fs::read(&path)?;
+                    if let Ok(encrypted_meta) = serde_json::from_slice::<EncryptedFileMeta>(&raw) {
+                        match decrypt_file_metadata(fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64) {
+                            Ok(payload) => {
+                                let eligible = match &server_time {
+                                    _ if payload.puzzle.is_some() => true,
+                                    Ok(server_time) => *server_time >= payload.file_unlock_date,
+                                    Err(_) => false,
+                                };
+                                if eligible
+                                    && blob_path(vault_path, &payload).exists()
+                                    && unlock_entry(vault_path, fek, &payload, out_path).await.is_ok()
+                                {
+                                    decrypted_files.push(payload.filename);
+                                }
+                            },
+                            Err(e) => {
+                                eprintln!("WARNING: Metadata integrity check failed");
+                                eprintln!("Possible tampering detected! Error: {}", e);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        match server_time {
+            Ok(server_time) => {
+                meta.last_verified_time = server_time;
+                meta.authenticated_time = server_time;
+                seal_vault_state(fek, &mut meta)?;
+                write_vault_metadata(vault_path, &meta)?;
+            }
+            Err(e) if decrypted_files.is_empty() => return Err(e),
+            Err(_) => {}
+        }
+
+        Ok(format!("Decrypted files: {:?}", decrypted_files))
+    }
+
+    /// [`unlock_file_with_verifier`] with this session's key.
+    pub async fn unlock_file(&self, out_dir: &str, filename: &str, verifier: &TimeVerifier) -> Result<String> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        let out_path = Path::new(out_dir);
+        let (_lock, mut meta) = self.begin()?;
+
+        // A solved time-lock puzzle is its own proof that time has passed, so
+        // puzzle-locked entries open without contacting any time source.
+        if let Some(payload) = find_entry(vault_path, fek, filename)? {
+            if payload.puzzle.is_some() {
+                fs::create_dir_all(out_path)?;
+                unlock_entry(vault_path, fek, &payload, out_path).await?;
+                return Ok(format!("Decrypted file: {}", filename));
+            }
+        }
+
+        let (server_time, _) = verifier.verify().await?;
+        let watermark = meta.time_watermark();
+        if watermark != 0 && server_time < watermark {
+            return Err(VaultError::TimeRegression.into());
+        }
+
+        fs::create_dir_all(out_path)?;
+
+        let fm_dir = files_meta_dir(vault_path);
+        let mut unlocked_one = false;
+
+        if fm_dir.exists() {
+            for entry in fs::read_dir(&fm_dir)? {
+                let path = entry?.path();
+                if is_entry_meta_file(&path) {
+                    let raw = fs::read(&path)?;
+                    if let Ok(encrypted_meta) = serde_json::from_slice::<EncryptedFileMeta>(&raw) {
+                        if let Ok(payload) = decrypt_file_metadata(fek, &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64) {
+                            if payload.filename == filename {
+                                if server_time < payload.file_unlock_date {
+                                    return Err(VaultError::StillLocked {
+                                        unlock_at: payload.file_unlock_date,
+                                    }
+                                    .into());
+                                }
+                                let locked_path = blob_path(vault_path, &payload);
+                                if locked_path.exists() {
+                                    unlock_entry(vault_path, fek, &payload, out_path).await?;
+                                    unlocked_one = true;
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        meta.last_verified_time = server_time;
+        meta.authenticated_time = server_time;
+        seal_vault_state(fek, &mut meta)?;
+        write_vault_metadata(vault_path, &meta)?;
+
+        if unlocked_one {
+            Ok(format!("Decrypted file: {}", filename))
+        } else {
+            Err(VaultError::FileNotFound { name: filename.to_string() }.into())
+        }
+    }
+
+    /// [`solve_puzzle`] with this session's key.
+    pub fn solve_puzzle(&self, filename: &str, checkpoint_secs: u64, mut on_checkpoint: impl FnMut(u64, u64) -> bool) -> Result<bool> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        // Solving can take hours, so the vault is only locked while it is read
+        // and while each checkpoint is written.
+        let (lock, _) = self.begin()?;
+        let payload = find_entry(vault_path, self.fek(), filename)?.ok_or_else(|| VaultError::FileNotFound { name: filename.to_string() })?;
+        let puzzle = payload
+            .puzzle
+            .as_ref()
+            .ok_or_else(|| anyhow!("File is not locked with a time-lock puzzle"))?;
+
+        let mut progress = match read_puzzle_progress(vault_path, &payload)? {
+            Some(progress) if progress.puzzle_id == puzzle.id() => progress,
+            _ => puzzle.start(),
+        };
+        drop(lock);
+        let batch = puzzle.calibrated_rate.max(1).saturating_mul(checkpoint_secs.max(1));
+
+        loop {
+            let solved = puzzle.advance(&mut progress, batch)?;
+            match VaultLock::acquire(vault_path) {
+                Ok(_lock) => {
+                    // The entry may have been deleted while the puzzle was
+                    // being solved. A renamed entry keeps its blob and puzzle.
+                    let current = read_entry(vault_path, fek, &payload.blob_id)?;
+                    if current.and_then(|current| current.puzzle).is_none_or(|current| current.id() != puzzle.id()) {
+                        return Err(VaultError::FileNotFound { name: filename.to_string() }.into());
+                    }
+                    write_puzzle_progress(vault_path, &payload, &progress)?;
+                    if solved {
+                        return Ok(true);
+                    }
+                }
+                // Another operation holds the vault: the squarings are kept
+                // and written at the next checkpoint instead.
+                Err(e) if matches!(e.downcast_ref::<VaultError>(), Some(VaultError::Busy { .. })) => {}
+                Err(e) => return Err(e),
+            }
+            if !on_checkpoint(progress.done, puzzle.squarings) {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// [`extend_unlock_date`] with this session's key.
+    pub fn extend_unlock_date(&self, filename: &str, new_unlock_date: u64) -> Result<()> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        let (_lock, _) = self.begin()?;
+        let mut payload = find_entry(vault_path, fek, filename)?.ok_or_else(|| VaultError::FileNotFound { name: filename.to_string() })?;
+        if payload.puzzle.is_some() {
+            return Err(anyhow!("The unlock date of a puzzle-locked file cannot be changed"));
+        }
+        if new_unlock_date < payload.file_unlock_date {
+            return Err(VaultError::UnlockDateEarlier {
+                unlock_at: payload.file_unlock_date,
+            }
+            .into());
+        }
+        if new_unlock_date == payload.file_unlock_date {
+            return Ok(());
+        }
+
+        payload.file_unlock_date = new_unlock_date;
+        write_entry_metadata(vault_path, fek, &payload)
+    }
+
+    /// [`rename_entry`] with this session's key.
+    pub fn rename_entry(&self, filename: &str, new_filename: &str) -> Result<()> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        let new_name = EntryName::new(new_filename)?;
+        let (_lock, _) = self.begin()?;
+        let mut payload = find_entry(vault_path, fek, filename)?.ok_or_else(|| VaultError::FileNotFound { name: filename.to_string() })?;
+        if new_name.as_str() == filename {
+            return Ok(());
+        }
+        if find_entry(vault_path, fek, new_name.as_str())?.is_some() {
+            return Err(VaultError::FileExists { name: new_name.into() }.into());
+        }
+
+        payload.filename = new_name.into();
+        write_entry_metadata(vault_path, fek, &payload)
+    }
+
+    /// [`delete_entry_with_verifier`] with this session's key.
+    pub async fn delete_entry(&self, filename: &str, verifier: &TimeVerifier) -> Result<()> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        let (_lock, mut meta) = self.begin()?;
+        let payload = find_entry(vault_path, fek, filename)?.ok_or_else(|| VaultError::FileNotFound { name: filename.to_string() })?;
+
+        if delete_needs_time(&meta, &payload) {
+            let server_time = record_verified_time(vault_path, fek, &mut meta, verifier).await?;
+            if server_time < payload.file_unlock_date {
+                return Err(VaultError::DeleteNotAllowed {
+                    unlock_at: payload.file_unlock_date,
+                }
+                .into());
+            }
+        }
+
+        journal::begin(vault_path, &PendingOp::Delete { blob_id: payload.blob_id.clone() })?;
+        remove_entry(vault_path, fek, &payload)?;
+        journal::commit(vault_path)
+    }
+
+    /// [`deletion_log`] with this session's key.
+    pub fn deletion_log(&self) -> Result<Vec<DeletionRecord>> {
+        let (_lock, _) = self.begin()?;
+        read_deletion_log(self.vault_dir())?
+            .iter()
+            .map(|record| {
+                let raw = decrypt_file_metadata_bytes(self.fek(), &record.encrypted_payload_b64, &record.metadata_nonce_b64)?;
+                Ok(serde_json::from_slice(&raw)?)
+            })
+            .collect()
+    }
+
+    /// [`set_delete_policy_with_verifier`] with this session's key.
+    pub async fn set_delete_policy(&self, policy: DeletePolicy, verifier: &TimeVerifier) -> Result<()> {
+        let vault_path = self.vault_dir();
+        let fek = self.fek();
+        let (_lock, mut meta) = self.begin()?;
+
+        if meta.delete_policy == DeletePolicy::UnlockedOnly && policy == DeletePolicy::Any {
+            let latest = latest_unlock_date(vault_path, fek)?;
+            if latest > meta.authenticated_time && record_verified_time(vault_path, fek, &mut meta, verifier).await? < latest {
+                return Err(VaultError::PolicyLocked { unlock_at: latest }.into());
+            }
+        }
+
+        meta.delete_policy = policy;
+        seal_vault_state(fek, &mut meta)?;
+        write_vault_metadata(vault_path, &meta)
+    }
+
+    /// [`get_status_with_password`] with this session's key.
+    pub fn status(&self) -> Result<Vec<serde_json::Value>> {
+        let vault_path = self.vault_dir();
+        let (_lock, _) = self.begin()?;
+
+        let fm_dir = files_meta_dir(vault_path);
+        let mut results = vec![];
+        let mut tampering_warnings = vec![];
+
+        if fm_dir.exists() {
+            for entry in fs::read_dir(fm_dir)? {
+                let path = entry?.path();
+                if is_entry_meta_file(&path) {
+                    let raw = fs::read(&path)?;
+                    match serde_json::from_slice::<EncryptedFileMeta>(&raw) {
+                        Ok(encrypted_meta) => {
+                            match decrypt_file_metadata(self.fek(), &encrypted_meta.encrypted_payload_b64, &encrypted_meta.metadata_nonce_b64) {
+                                Ok(payload) => {
+                                    results.push(serde_json::json!({
+                                        "filename": payload.filename,
+                                        "file_size": payload.file_size,
+                                        "file_unlock_date": payload.file_unlock_date,
+                                        "content_sha256": payload.content_sha256,
+                                        "time_lock_round": payload.time_lock.as_ref().map(|lock| lock.round),
+                                        "puzzle": payload.puzzle.as_ref().map(|puzzle| serde_json::json!({
+                                            "squarings": puzzle.squarings,
+                                            "done": read_puzzle_progress(vault_path, &payload)
+                                                .ok()
+                                                .flatten()
+                                                .filter(|progress| progress.puzzle_id == puzzle.id())
+                                                .map_or(0, |progress| progress.done),
+                                        })),
+                                    }));
+                                },
+                                Err(e) => {
+                                    let warning_msg = format!("WARNING: Metadata decryption failed for file: {:?}", path.file_name());
+                                    eprintln!("{}", warning_msg);
+                                    eprintln!("Possible tampering detected! Error: {}", e);
+                                    tampering_warnings.push(warning_msg);
+                                    tampering_warnings.push("Error: Metadata decryption failed - Possible tampering detected!".to_string());
+                                }
+                            }
+                        },
+                        Err(e) => {
+                            let warning_msg = format!("WARNING: Invalid metadata format: {:?} - {}", path.file_name(), e);
+                            eprintln!("{}", warning_msg);
+                            tampering_warnings.push(warning_msg);
+                        }
+                    }
+                }
+            }
+        }
+
+        if !tampering_warnings.is_empty() {
+            results.push(serde_json::json!({
+                "_tampering_warnings": tampering_warnings
+            }));
+        }
+
+        Ok(results)
+    }
+}
+
+/// Re-wraps `fek` under a key derived from `password` with a fresh salt and
+/// the Argon2 parameters currently in `meta`, then reseals and atomically
+/// rewrites the header.
+pub(crate) fn rewrap_fek(vault_path: &Path, meta: &mut VaultMetadata, fek: &[u8; 32], password: &str) -> Result<()> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut derived = derive_key(password, &salt, meta.argon_mem_kib, meta.argon_iters, meta.argon_parallelism)?;
+    let wrapped = wrap_fek(&derived, fek);
+    derived.zeroize();
+    let (wrapped_fek_b64, wrap_nonce_b64) = wrapped?;
+
+    meta.salt_b64 = general_purpose::STANDARD.encode(salt);
+    meta.wrapped_fek_b64 = wrapped_fek_b64;
+    meta.wrap_nonce_b64 = wrap_nonce_b64;
+    salt.zeroize();
+    seal_vault_state(fek, meta)?;
+    write_vault_metadata(vault_path, meta)
+}
+
+/// Changes the vault password from `old_password` to `new_password`.
+///
+/// The FEK is unwrapped with the old password and re-wrapped under a key
+/// derived from the new one with a fresh salt; entries are not re-encrypted.
+/// The new header replaces `vault_metadata.json` atomically, so an
+/// interruption leaves the vault openable with exactly one of the passwords.
+pub fn change_password(vault_dir: String, old_password: String, new_password: String) -> Result<()> {
+    if new_password.is_empty() {
+        return Err(anyhow!("New password must not be empty"));
+    }
+    let vault_path = Path::new(&vault_dir);
+    let _lock = VaultLock::acquire(vault_path)?;
+    let (mut meta, mut fek_arr) = open_vault_key(vault_path, &old_password)?;
+    let result = rewrap_fek(vault_path, &mut meta, &fek_arr, &new_password);
+    fek_arr.zeroize();
+    result
+}
+
+/// Raises the vault's Argon2 parameters to values calibrated on this machine
+/// to take about `target_ms` per unlock, and re-wraps the FEK under them.
+///
+/// Parameters are never lowered: each of memory, iterations and lanes is at
+/// least its current value and the default. The calibration is recorded in
+/// [`VaultMetadata::argon_calibration`].
+pub fn upgrade_argon_params(vault_dir: String, password: String, target_ms: u64) -> Result<ArgonCalibration> {
+    let vault_path = Path::new(&vault_dir);
+    let _lock = VaultLock::acquire(vault_path)?;
+    let (mut meta, mut fek_arr) = open_vault_key(vault_path, &password)?;
+
+    let (default_mem_kib, default_iters, default_parallelism) = default_argon_params();
+    let minimum = (
+        meta.argon_mem_kib.max(default_mem_kib),
+        meta.argon_iters.max(default_iters),
+        meta.argon_parallelism.max(default_parallelism),
+    );
+    let result = calibrate_argon_params(target_ms, minimum).and_then(|calibration| {
+        meta.argon_mem_kib = calibration.mem_kib;
+        meta.argon_iters = calibration.iters;
+        meta.argon_parallelism = calibration.parallelism;
+        meta.argon_calibration = Some(calibration.clone());
+        rewrap_fek(vault_path, &mut meta, &fek_arr, &password)?;
+        Ok(calibration)
+    });
+
+    fek_arr.zeroize();
+    result
+}
+
+/// Checks that `password` unwraps the vault key.
+pub fn verify_password(vault_dir: String, password: String) -> Result<()> {
+    open_vault(vault_dir, password)?;
+    Ok(())
+}
+
+/// Returns the vault's creation and last verified time, or `None` if
+/// `vault_dir` does not contain a vault.
+pub fn vault_info(vault_dir: String) -> Result<Option<VaultInfo>> {
+    let vault_path = Path::new(&vault_dir);
+    if !vault_meta_path(vault_path).exists() {
+        return Ok(None);
+    }
+
+    let meta = read_vault_metadata(vault_path)?;
+    Ok(Some(VaultInfo {
+        created: meta.creation_ts,
+        last_server_time: meta.time_watermark(),
+        time_source: None,
+    }))
+}
+
+/// Fetches the current public time and records it as the vault's last
+/// verified time, rejecting any regression.
+pub async fn refresh_server_time(vault_dir: String) -> Result<VaultInfo> {
+    refresh_server_time_with_verifier(vault_dir, &TimeVerifier::default()).await
+}
+
+/// [`refresh_server_time`] with the current time confirmed by `verifier`.
+pub async fn refresh_server_time_with_verifier(vault_dir: String, verifier: &TimeVerifier) -> Result<VaultInfo> {
+    let vault_path = Path::new(&vault_dir);
+    let _lock = VaultLock::acquire(vault_path)?;
+
+    let mut meta = read_vault_metadata(vault_path)?;
+    let (server_time, source) = verifier.verify().await?;
+
+    let watermark = meta.time_watermark();
+    if watermark != 0 && server_time < watermark {
+        return Err(VaultError::TimeRegression.into());
+    }
+
+    meta.last_verified_time = server_time;
+    write_vault_metadata(vault_path, &meta)?;
+
+    Ok(VaultInfo {
+        created: meta.creation_ts,
+        last_server_time: server_time,
+        time_source: Some(source),
+    })
+}
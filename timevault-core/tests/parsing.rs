@@ -0,0 +1,347 @@
+//! Property tests for the parsers that read attacker-controllable input:
+//! the vault header, encrypted entry metadata and time-server responses,
+//! including the Roughtime and NTP datagrams.
+//!
+//! Each parser must round-trip what TimeVault writes, reject anything that
+//! has been altered, and never panic on arbitrary input. The fuzz targets in
+//! `timevault-core/fuzz` exercise the same parsers without a fixed budget.
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::DateTime;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use serde_json::{json, Value};
+
+use timevault_core::crypto::{decrypt_file_metadata, encrypt_file_metadata};
+use timevault_core::metadata::parse_vault_metadata;
+use timevault_core::puzzle::RswPuzzle;
+use timevault_core::time::ntp::{self, Reply, PACKET_LEN};
+use timevault_core::time::roughtime::{self, Message, NONCE_LEN};
+use timevault_core::time::{JsonTimeApi, JsonTimeFormat};
+use timevault_core::{DeletePolicy, FileMetaPayload, VaultError, VaultMetadata, WrappedKey, VAULT_VERSION};
+
+const FEK: [u8; 32] = [0x5a; 32];
+
+/// Last second of the year 9999, the latest time RFC 3339 can express.
+const MAX_RFC3339_SECS: u64 = 253_402_300_799;
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+/// Seconds between the NTP epoch (1900) and the Unix epoch.
+const NTP_UNIX_OFFSET: i128 = 2_208_988_800;
+/// 2020-01-01, a local clock reading for NTP exchanges.
+const T1_NANOS: i128 = 1_577_836_800 * NANOS_PER_SEC;
+
+fn b64(bytes: &[u8]) -> String {
+    general_purpose::STANDARD.encode(bytes)
+}
+
+fn arb_b64() -> impl Strategy<Value = String> {
+    vec(any::<u8>(), 0..48).prop_map(|bytes| b64(&bytes))
+}
+
+fn arb_vault_metadata() -> impl Strategy<Value = VaultMetadata> {
+    (
+        (1..=VAULT_VERSION, arb_b64(), 32..=1024 * 1024u32, 1..=64u32, 1..=4u32, arb_b64(), arb_b64()),
+        (any::<u64>(), any::<u64>(), any::<u64>(), arb_b64(), any::<bool>()),
+    )
+        .prop_map(
+            |(
+                (version, salt_b64, argon_mem_kib, argon_iters, argon_parallelism, wrapped_fek_b64, wrap_nonce_b64),
+                (creation_ts, last_verified_time, authenticated_time, state_mac_b64, locked_only),
+            )| VaultMetadata {
+                version,
+                salt_b64,
+                argon_mem_kib,
+                argon_iters,
+                argon_parallelism,
+                wrapped_fek_b64,
+                wrap_nonce_b64,
+                creation_ts,
+                last_verified_time,
+                authenticated_time,
+                state_mac_b64,
+                argon_calibration: None,
+                delete_policy: if locked_only { DeletePolicy::UnlockedOnly } else { DeletePolicy::Any },
+            },
+        )
+}
+
+fn arb_puzzle() -> impl Strategy<Value = RswPuzzle> {
+    ("[0-9a-f]{0,64}", "[0-9a-f]{0,64}", any::<u64>(), any::<u64>(), arb_b64(), arb_b64()).prop_map(
+        |(modulus_hex, base_hex, squarings, calibrated_rate, nonce_b64, wrapped_key_b64)| RswPuzzle {
+            modulus_hex,
+            base_hex,
+            squarings,
+            calibrated_rate,
+            nonce_b64,
+            wrapped_key_b64,
+        },
+    )
+}
+
+fn arb_payload() -> impl Strategy<Value = FileMetaPayload> {
+    (
+        (any::<String>(), any::<u64>(), any::<u64>(), "[0-9a-f]{64}", "[0-9a-f]{32}", arb_b64()),
+        (
+            proptest::option::of(1..=u32::MAX),
+            proptest::option::of((arb_b64(), arb_b64())),
+            proptest::option::of(arb_puzzle()),
+        ),
+    )
+        .prop_map(
+            |((filename, file_size, file_unlock_date, content_sha256, blob_id, nonce_b64), (chunk_size, data_key, puzzle))| FileMetaPayload {
+                filename,
+                file_size,
+                file_unlock_date,
+                content_sha256,
+                blob_id,
+                nonce_b64,
+                chunk_size,
+                data_key: data_key.map(|(nonce_b64, wrapped_key_b64)| WrappedKey { nonce_b64, wrapped_key_b64 }),
+                time_lock: None,
+                puzzle,
+            },
+        )
+}
+
+/// Arbitrary JSON, nested a few levels deep.
+fn arb_json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        any::<String>().prop_map(Value::from),
+    ];
+    leaf.prop_recursive(3, 32, 4, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..4).prop_map(Value::from),
+            vec(
+                (
+                    prop_oneof![Just("unixtime".to_string()), Just("dateTime".to_string()), Just("currentDateTime".to_string()), Just("currentFileTime".to_string()), any::<String>()],
+                    inner
+                ),
+                0..4
+            )
+            .prop_map(|fields| Value::Object(fields.into_iter().collect())),
+        ]
+    })
+}
+
+/// Encodes Unix nanoseconds as a 64-bit NTP timestamp.
+fn ntp_timestamp(unix_nanos: i128) -> [u8; 8] {
+    let secs = (unix_nanos.div_euclid(NANOS_PER_SEC) + NTP_UNIX_OFFSET) as u32;
+    let frac = ((unix_nanos.rem_euclid(NANOS_PER_SEC) << 32) / NANOS_PER_SEC) as u32;
+    let mut out = [0u8; 8];
+    out[..4].copy_from_slice(&secs.to_be_bytes());
+    out[4..].copy_from_slice(&frac.to_be_bytes());
+    out
+}
+
+/// A client request with `origin` as its transmit timestamp, and a server
+/// reply to it that received at `t2` and sent at `t3` (Unix nanoseconds).
+fn ntp_exchange(origin: [u8; 8], t2: i128, t3: i128) -> ([u8; PACKET_LEN], [u8; PACKET_LEN]) {
+    let mut request = [0u8; PACKET_LEN];
+    request[0] = (4 << 3) | 3;
+    request[40..48].copy_from_slice(&origin);
+
+    let mut reply = [0u8; PACKET_LEN];
+    reply[0] = (4 << 3) | 4;
+    reply[1] = 2;
+    reply[24..32].copy_from_slice(&origin);
+    reply[32..40].copy_from_slice(&ntp_timestamp(t2));
+    reply[40..48].copy_from_slice(&ntp_timestamp(t3));
+    (request, reply)
+}
+
+fn api(format: JsonTimeFormat) -> JsonTimeApi {
+    JsonTimeApi::new("test", "https://time.example", format)
+}
+
+fn rfc3339(unix_secs: u64) -> String {
+    DateTime::from_timestamp(unix_secs as i64, 0).unwrap().to_rfc3339()
+}
+
+fn as_json<T: serde::Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap()
+}
+
+proptest! {
+    #[test]
+    fn vault_metadata_round_trips(meta in arb_vault_metadata()) {
+        let raw = serde_json::to_vec(&meta).unwrap();
+        prop_assert_eq!(as_json(&parse_vault_metadata(&raw).unwrap()), as_json(&meta));
+    }
+
+    #[test]
+    fn vault_metadata_rejects_unknown_versions(meta in arb_vault_metadata(), version in VAULT_VERSION + 1..=u8::MAX) {
+        let mut json = as_json(&meta);
+        json["version"] = json!(version);
+        let err = VaultError::from(parse_vault_metadata(&serde_json::to_vec(&json).unwrap()).unwrap_err());
+        prop_assert!(matches!(err, VaultError::UnsupportedVersion { version: v } if v == version), "{:?}", err);
+    }
+
+    #[test]
+    fn vault_metadata_rejects_impossible_headers(meta in arb_vault_metadata(), argon_mem_kib in 1024 * 1024 + 1..=u32::MAX) {
+        for (field, value) in [("version", 0), ("argon_mem_kib", argon_mem_kib)] {
+            let mut json = as_json(&meta);
+            json[field] = json!(value);
+            let err = VaultError::from(parse_vault_metadata(&serde_json::to_vec(&json).unwrap()).unwrap_err());
+            prop_assert!(matches!(err, VaultError::CorruptMetadata { .. }), "{}: {:?}", field, err);
+        }
+    }
+
+    #[test]
+    fn vault_metadata_rejects_unbounded_argon_params(meta in arb_vault_metadata(), iters in 65..=u32::MAX, parallelism in 17..=u32::MAX) {
+        for (field, value) in [("argon_iters", 0), ("argon_iters", iters), ("argon_parallelism", 0), ("argon_parallelism", parallelism)] {
+            let mut json = as_json(&meta);
+            json[field] = json!(value);
+            let err = VaultError::from(parse_vault_metadata(&serde_json::to_vec(&json).unwrap()).unwrap_err());
+            prop_assert!(matches!(err, VaultError::CorruptMetadata { .. }), "{} = {}: {:?}", field, value, err);
+        }
+
+        let mut json = as_json(&meta);
+        json["argon_parallelism"] = json!(16);
+        json["argon_mem_kib"] = json!(127);
+        prop_assert!(parse_vault_metadata(&serde_json::to_vec(&json).unwrap()).is_err());
+    }
+
+    #[test]
+    fn vault_metadata_rejects_truncation(meta in arb_vault_metadata(), cut in any::<prop::sample::Index>()) {
+        let raw = serde_json::to_vec(&meta).unwrap();
+        prop_assert!(parse_vault_metadata(&raw[..cut.index(raw.len())]).is_err());
+    }
+
+    #[test]
+    fn vault_metadata_survives_arbitrary_bytes(raw in vec(any::<u8>(), 0..512)) {
+        let _ = parse_vault_metadata(&raw);
+    }
+
+    #[test]
+    fn vault_metadata_survives_arbitrary_json(json in arb_json()) {
+        let _ = parse_vault_metadata(&serde_json::to_vec(&json).unwrap());
+    }
+
+    #[test]
+    fn entry_metadata_round_trips(payload in arb_payload()) {
+        let (encrypted_b64, nonce_b64) = encrypt_file_metadata(&FEK, &payload).unwrap();
+        let decrypted = decrypt_file_metadata(&FEK, &encrypted_b64, &nonce_b64).unwrap();
+        prop_assert_eq!(as_json(&decrypted), as_json(&payload));
+    }
+
+    #[test]
+    fn entry_metadata_rejects_modification(payload in arb_payload(), at in any::<prop::sample::Index>(), flip in 1..=u8::MAX) {
+        let (encrypted_b64, nonce_b64) = encrypt_file_metadata(&FEK, &payload).unwrap();
+        let mut encrypted = general_purpose::STANDARD.decode(&encrypted_b64).unwrap();
+        let mut nonce = general_purpose::STANDARD.decode(&nonce_b64).unwrap();
+
+        let i = at.index(encrypted.len());
+        encrypted[i] ^= flip;
+        let err = VaultError::from(decrypt_file_metadata(&FEK, &b64(&encrypted), &nonce_b64).unwrap_err());
+        prop_assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+        encrypted[i] ^= flip;
+
+        let j = at.index(nonce.len());
+        nonce[j] ^= flip;
+        prop_assert!(decrypt_file_metadata(&FEK, &b64(&encrypted), &b64(&nonce)).is_err());
+
+        prop_assert!(decrypt_file_metadata(&FEK, &b64(&encrypted[..encrypted.len() - 1]), &nonce_b64).is_err());
+        prop_assert!(decrypt_file_metadata(&[0xa5; 32], &encrypted_b64, &nonce_b64).is_err());
+    }
+
+    #[test]
+    fn entry_metadata_rejects_wrong_nonce_lengths(payload in arb_payload(), len in (0..64usize).prop_filter("valid length", |len| *len != 24)) {
+        let (encrypted_b64, _) = encrypt_file_metadata(&FEK, &payload).unwrap();
+        prop_assert!(decrypt_file_metadata(&FEK, &encrypted_b64, &b64(&vec![0; len])).is_err());
+    }
+
+    #[test]
+    fn entry_metadata_survives_arbitrary_input(encrypted_b64 in any::<String>(), nonce_b64 in any::<String>(), encrypted in vec(any::<u8>(), 0..256), nonce in vec(any::<u8>(), 0..32)) {
+        prop_assert!(decrypt_file_metadata(&FEK, &encrypted_b64, &nonce_b64).is_err());
+        prop_assert!(decrypt_file_metadata(&FEK, &b64(&encrypted), &b64(&nonce)).is_err());
+    }
+
+    #[test]
+    fn time_responses_round_trip(unix_secs in 0..=MAX_RFC3339_SECS) {
+        prop_assert_eq!(api(JsonTimeFormat::UnixTime).parse(&json!({ "unixtime": unix_secs })), Some(unix_secs));
+        prop_assert_eq!(api(JsonTimeFormat::DateTime).parse(&json!({ "dateTime": rfc3339(unix_secs) })), Some(unix_secs));
+        prop_assert_eq!(api(JsonTimeFormat::WorldClock).parse(&json!({ "currentDateTime": rfc3339(unix_secs) })), Some(unix_secs));
+        let filetime = (unix_secs + 11_644_473_600) * 10_000_000;
+        prop_assert_eq!(api(JsonTimeFormat::WorldClock).parse(&json!({ "currentFileTime": filetime })), Some(unix_secs));
+    }
+
+    #[test]
+    fn time_responses_reject_times_before_1970(before in 1..=62_135_596_800i64) {
+        prop_assert_eq!(api(JsonTimeFormat::UnixTime).parse(&json!({ "unixtime": -before })), None);
+        let date = DateTime::from_timestamp(-before, 0).unwrap().to_rfc3339();
+        prop_assert_eq!(api(JsonTimeFormat::DateTime).parse(&json!({ "dateTime": date })), None);
+        prop_assert_eq!(api(JsonTimeFormat::WorldClock).parse(&json!({ "currentDateTime": date })), None);
+    }
+
+    #[test]
+    fn time_responses_reject_wrong_types(value in arb_json().prop_filter("not a time", |v| !v.is_i64() && !v.is_u64() && !v.is_string())) {
+        prop_assert_eq!(api(JsonTimeFormat::UnixTime).parse(&json!({ "unixtime": value })), None);
+        prop_assert_eq!(api(JsonTimeFormat::DateTime).parse(&json!({ "dateTime": value })), None);
+        prop_assert_eq!(api(JsonTimeFormat::WorldClock).parse(&json!({ "currentDateTime": value, "currentFileTime": value })), None);
+    }
+
+    #[test]
+    fn time_responses_survive_arbitrary_json(json in arb_json()) {
+        for format in [JsonTimeFormat::UnixTime, JsonTimeFormat::DateTime, JsonTimeFormat::WorldClock] {
+            let _ = api(format).parse(&json);
+        }
+    }
+
+    #[test]
+    fn ntp_replies_give_the_clock_offset(origin in any::<[u8; 8]>(), offset_ms in -86_400_000..=86_400_000i128, delay_ms in 0..=2_000i128, hold_ms in 0..=100i128) {
+        // The server's clock is `offset_ms` ahead of ours, the request and
+        // reply each take half the round trip and the server holds the
+        // request for `hold_ms`.
+        let offset = offset_ms * 1_000_000;
+        let t2 = T1_NANOS + offset + delay_ms * 500_000;
+        let t3 = t2 + hold_ms * 1_000_000;
+        let t4 = t3 - offset + delay_ms * 500_000;
+        let (request, reply) = ntp_exchange(origin, t2, t3);
+
+        let Reply::Time { offset: measured, delay, .. } = ntp::parse_reply(&request, &reply, T1_NANOS, t4).unwrap() else {
+            panic!("expected a time reply");
+        };
+        prop_assert!((measured - offset).abs() <= 2, "{} vs {}", measured, offset);
+        prop_assert!((delay - delay_ms * 1_000_000).abs() <= 2, "{} vs {}", delay, delay_ms);
+    }
+
+    #[test]
+    fn ntp_replies_must_echo_the_request(origin in any::<[u8; 8]>(), at in 0..8usize, flip in 1..=u8::MAX) {
+        let (mut request, reply) = ntp_exchange(origin, T1_NANOS, T1_NANOS);
+        request[40 + at] ^= flip;
+        prop_assert!(ntp::parse_reply(&request, &reply, T1_NANOS, T1_NANOS).is_err());
+    }
+
+    #[test]
+    fn ntp_kisses_are_reported(origin in any::<[u8; 8]>(), code in "[A-Z]{4}") {
+        let (request, mut reply) = ntp_exchange(origin, T1_NANOS, T1_NANOS);
+        reply[1] = 0;
+        reply[12..16].copy_from_slice(code.as_bytes());
+        let kiss = ntp::parse_reply(&request, &reply, T1_NANOS, T1_NANOS).unwrap();
+        prop_assert!(matches!(&kiss, Reply::Kiss(c) if *c == code), "{:?}", kiss);
+    }
+
+    #[test]
+    fn ntp_replies_survive_arbitrary_bytes(request in any::<[u8; PACKET_LEN]>(), reply in any::<[u8; PACKET_LEN]>(), t1 in any::<i64>(), t4 in any::<i64>()) {
+        let _ = ntp::parse_reply(&request, &reply, t1 as i128, t4 as i128);
+    }
+
+    #[test]
+    fn roughtime_messages_survive_arbitrary_bytes(bytes in vec(any::<u8>(), 0..512)) {
+        if let Ok(message) = Message::parse(&bytes) {
+            let _ = message.get(u32::from_le_bytes(*b"NONC"));
+        }
+    }
+
+    #[test]
+    fn roughtime_responses_survive_arbitrary_bytes(key in any::<[u8; 32]>(), nonce in vec(any::<u8>(), NONCE_LEN), bytes in vec(any::<u8>(), 0..1024)) {
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().unwrap();
+        prop_assert!(roughtime::verify_response(&key, &nonce, &bytes).is_err());
+    }
+}
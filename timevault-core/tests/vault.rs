@@ -0,0 +1,645 @@
+//! End-to-end tests of the vault operations against real vault directories.
+//!
+//! Time comes from a fixed clock injected through [`TimeVerifier::new`], so
+//! nothing here touches the network.
+
+use anyhow::Result;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+use timevault_core::stream::DEFAULT_CHUNK_SIZE;
+use timevault_core::time::BoxFuture;
+use timevault_core::{
+    add_file, add_file_with_name, change_password, default_argon_params, delete_entry_with_verifier, deletion_log, extend_unlock_date,
+    get_status_with_password, init_vault, open_vault, rename_entry, set_delete_policy_with_verifier, unlock_file_with_verifier,
+    unlock_vault_with_verifier, upgrade_argon_params, verify_password, DeletePolicy, TimePolicy, TimeSample, TimeSource, TimeVerifier,
+    VaultError, VaultSession,
+};
+
+const PASSWORD: &str = "correct horse battery staple";
+
+/// A time source that always reports the same time.
+struct FixedClock(u64);
+
+impl TimeSource for FixedClock {
+    fn name(&self) -> String {
+        "fixed clock".to_string()
+    }
+
+    fn fetch(&self) -> BoxFuture<'_, Result<TimeSample>> {
+        let sample = TimeSample {
+            unix_secs: self.0,
+            source: self.name(),
+            authenticated: true,
+        };
+        Box::pin(async move { Ok(sample) })
+    }
+}
+
+/// A verifier that trusts a single clock reading `unix_secs`.
+fn clock_at(unix_secs: u64) -> TimeVerifier {
+    let policy = TimePolicy {
+        min_agreeing: 1,
+        ..TimePolicy::default()
+    };
+    TimeVerifier::new(vec![Box::new(FixedClock(unix_secs))], policy)
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+/// A fresh vault in a temporary directory, with room alongside it for
+/// plaintext inputs and decrypted output.
+struct Fixture {
+    _root: TempDir,
+    vault: PathBuf,
+    input: PathBuf,
+    out: PathBuf,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let root = tempfile::tempdir().unwrap();
+        let vault = root.path().join("vault");
+        let input = root.path().join("input");
+        let out = root.path().join("out");
+        fs::create_dir_all(&input).unwrap();
+        init_vault(s(&vault), PASSWORD.to_string()).unwrap();
+        Self {
+            _root: root,
+            vault,
+            input,
+            out,
+        }
+    }
+
+    fn vault_dir(&self) -> String {
+        s(&self.vault)
+    }
+
+    fn out_dir(&self) -> String {
+        s(&self.out)
+    }
+
+    /// Writes `contents` to a plaintext file named `name` and returns its path.
+    fn plaintext(&self, name: &str, contents: &[u8]) -> String {
+        let path = self.input.join(name);
+        fs::write(&path, contents).unwrap();
+        s(&path)
+    }
+
+    /// Adds a file named `name` holding `contents`, unlocking at `unlock_at`.
+    fn add(&self, name: &str, contents: &[u8], unlock_at: u64) {
+        add_file(self.vault_dir(), self.plaintext(name, contents), PASSWORD.to_string(), unlock_at).unwrap();
+    }
+
+    async fn delete(&self, name: &str, verifier: &TimeVerifier) -> Result<()> {
+        delete_entry_with_verifier(self.vault_dir(), PASSWORD.to_string(), name.to_string(), verifier).await
+    }
+
+    async fn set_policy(&self, policy: DeletePolicy, verifier: &TimeVerifier) -> Result<()> {
+        set_delete_policy_with_verifier(self.vault_dir(), PASSWORD.to_string(), policy, verifier).await
+    }
+
+    fn entry_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = get_status_with_password(self.vault_dir(), PASSWORD.to_string())
+            .unwrap()
+            .iter()
+            .filter_map(|entry| entry["filename"].as_str().map(str::to_string))
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn only_file_in(&self, dir: &str) -> PathBuf {
+        let mut files: Vec<PathBuf> = fs::read_dir(self.vault.join(dir))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file())
+            .collect();
+        assert_eq!(files.len(), 1, "expected a single file in {}", dir);
+        files.pop().unwrap()
+    }
+}
+
+fn s(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn vault_error(result: Result<impl std::fmt::Debug>) -> VaultError {
+    VaultError::from(result.expect_err("operation should have failed"))
+}
+
+/// Content spanning several stream chunks, with a partial last chunk.
+fn large_content() -> Vec<u8> {
+    (0..200_000u32).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn added_files_are_listed() {
+    let vault = Fixture::new();
+    assert!(vault.entry_names().is_empty());
+
+    vault.add("a.txt", b"first", now() - 60);
+    vault.add("b.bin", &large_content(), now() + 3600);
+
+    assert_eq!(vault.entry_names(), ["a.txt", "b.bin"]);
+    let status = get_status_with_password(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+    let b = status.iter().find(|entry| entry["filename"] == "b.bin").unwrap();
+    assert_eq!(b["file_size"], 200_000);
+}
+
+#[tokio::test]
+async fn unlock_file_round_trips_content() {
+    let vault = Fixture::new();
+    let content = large_content();
+    vault.add("big.bin", &content, now() - 60);
+    vault.add("empty.txt", b"", now() - 60);
+
+    let verifier = clock_at(now());
+    for (name, expected) in [("big.bin", &content[..]), ("empty.txt", &[][..])] {
+        unlock_file_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), name.to_string(), &verifier).await.unwrap();
+        assert_eq!(fs::read(vault.out.join(name)).unwrap(), expected);
+    }
+}
+
+#[tokio::test]
+async fn unlock_vault_decrypts_only_due_entries() {
+    let vault = Fixture::new();
+    vault.add("due.txt", b"open me", now() - 60);
+    vault.add("later.txt", b"not yet", now() + 3600);
+
+    unlock_vault_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), &clock_at(now())).await.unwrap();
+
+    assert_eq!(fs::read(vault.out.join("due.txt")).unwrap(), b"open me");
+    assert!(!vault.out.join("later.txt").exists());
+}
+
+#[tokio::test]
+async fn custom_name_is_used_for_entry_and_output() {
+    let vault = Fixture::new();
+    let source = vault.plaintext("source.txt", b"renamed");
+    add_file_with_name(vault.vault_dir(), source, PASSWORD.to_string(), now() - 60, Some("stored.txt".to_string())).unwrap();
+
+    assert_eq!(vault.entry_names(), ["stored.txt"]);
+    unlock_file_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), "stored.txt".to_string(), &clock_at(now())).await.unwrap();
+    assert_eq!(fs::read(vault.out.join("stored.txt")).unwrap(), b"renamed");
+}
+
+#[tokio::test]
+async fn session_runs_several_operations() {
+    let vault = Fixture::new();
+    let session = open_vault(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+    session.add_file(&vault.plaintext("one.txt", b"1"), now() - 60, None).unwrap();
+    session.add_file(&vault.plaintext("two.txt", b"2"), now() - 60, None).unwrap();
+    session.rename_entry("two.txt", "three.txt").unwrap();
+
+    session.unlock_file(&vault.out_dir(), "three.txt", &clock_at(now())).await.unwrap();
+    assert_eq!(fs::read(vault.out.join("three.txt")).unwrap(), b"2");
+    assert_eq!(vault.entry_names(), ["one.txt", "three.txt"]);
+}
+
+/// Adds `name` to `session` locked in a puzzle that takes a few seconds to
+/// solve.
+fn add_puzzle_locked(vault: &Fixture, session: &VaultSession, name: &str, contents: &[u8]) {
+    session.add_file_puzzle_locked(&vault.plaintext(name, contents), now() + 3, None).unwrap();
+}
+
+#[tokio::test]
+async fn puzzle_solves_wait_out_a_busy_vault() {
+    let vault = Fixture::new();
+    let session = open_vault(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+    add_puzzle_locked(&vault, &session, "p.txt", b"patience");
+
+    // Another process holds the vault across one checkpoint.
+    let mut holder = None;
+    let mut checkpoints = 0;
+    let solved = session
+        .solve_puzzle("p.txt", 1, |_, _| {
+            checkpoints += 1;
+            holder = match holder.take() {
+                None if checkpoints == 1 => {
+                    let file = fs::OpenOptions::new().write(true).open(vault.vault.join("vault.lock")).unwrap();
+                    file.lock().unwrap();
+                    Some(file)
+                }
+                _ => None,
+            };
+            true
+        })
+        .unwrap();
+    assert!(solved);
+    assert!(checkpoints >= 2, "{}", checkpoints);
+
+    session.unlock_file(&vault.out_dir(), "p.txt", &no_clock()).await.unwrap();
+    assert_eq!(fs::read(vault.out.join("p.txt")).unwrap(), b"patience");
+}
+
+#[test]
+fn puzzle_solves_stop_when_the_entry_is_deleted() {
+    let vault = Fixture::new();
+    let session = open_vault(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+    add_puzzle_locked(&vault, &session, "p.txt", b"patience");
+
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let err = vault_error(session.solve_puzzle("p.txt", 1, |_, _| {
+        runtime.block_on(session.delete_entry("p.txt", &no_clock())).unwrap();
+        true
+    }));
+    assert!(matches!(&err, VaultError::FileNotFound { name } if name == "p.txt"), "{:?}", err);
+    assert_eq!(fs::read_dir(vault.vault.join("puzzles")).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn wrong_password_is_rejected() {
+    let vault = Fixture::new();
+    vault.add("a.txt", b"secret", now() - 60);
+
+    let err = vault_error(verify_password(vault.vault_dir(), "wrong".to_string()));
+    assert!(matches!(err, VaultError::WrongPassword), "{:?}", err);
+
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        "wrong".to_string(),
+        "a.txt".to_string(),
+        &clock_at(now()),
+    ).await);
+    assert!(matches!(err, VaultError::WrongPassword), "{:?}", err);
+    assert!(!vault.out.join("a.txt").exists());
+}
+
+#[tokio::test]
+async fn changed_password_replaces_the_old_one() {
+    let vault = Fixture::new();
+    vault.add("a.txt", b"secret", now() - 60);
+    let new_password = "new horse battery staple";
+
+    change_password(vault.vault_dir(), PASSWORD.to_string(), new_password.to_string()).unwrap();
+
+    let err = vault_error(verify_password(vault.vault_dir(), PASSWORD.to_string()));
+    assert!(matches!(err, VaultError::WrongPassword), "{:?}", err);
+    unlock_file_with_verifier(vault.vault_dir(), vault.out_dir(), new_password.to_string(), "a.txt".to_string(), &clock_at(now())).await.unwrap();
+    assert_eq!(fs::read(vault.out.join("a.txt")).unwrap(), b"secret");
+}
+
+#[tokio::test]
+async fn upgraded_argon_params_are_recorded_and_keep_the_password() {
+    let vault = Fixture::new();
+    vault.add("a.txt", b"secret", now() - 60);
+
+    // A 1 ms target cannot be met, so the defaults are kept as the floor.
+    let calibration = upgrade_argon_params(vault.vault_dir(), PASSWORD.to_string(), 1).unwrap();
+    let (mem_kib, iters, parallelism) = default_argon_params();
+    assert!(calibration.mem_kib >= mem_kib && calibration.iters >= iters && calibration.parallelism >= parallelism, "{:?}", calibration);
+
+    let header: serde_json::Value = serde_json::from_slice(&fs::read(vault.vault.join("vault_metadata.json")).unwrap()).unwrap();
+    assert_eq!(header["argon_mem_kib"], calibration.mem_kib);
+    assert_eq!(header["argon_iters"], calibration.iters);
+    assert_eq!(header["argon_calibration"]["target_ms"], 1);
+
+    unlock_file_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), "a.txt".to_string(), &clock_at(now())).await.unwrap();
+    assert_eq!(fs::read(vault.out.join("a.txt")).unwrap(), b"secret");
+}
+
+#[tokio::test]
+async fn unlock_dates_can_only_be_extended() {
+    let vault = Fixture::new();
+    let unlock_at = now() + 3600;
+    vault.add("a.txt", b"secret", unlock_at);
+
+    let err = vault_error(extend_unlock_date(vault.vault_dir(), PASSWORD.to_string(), "a.txt".to_string(), unlock_at - 1));
+    assert!(matches!(err, VaultError::UnlockDateEarlier { unlock_at: at } if at == unlock_at), "{:?}", err);
+
+    // Past the original date, but not the extended one.
+    extend_unlock_date(vault.vault_dir(), PASSWORD.to_string(), "a.txt".to_string(), unlock_at + 3600).unwrap();
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "a.txt".to_string(),
+        &clock_at(unlock_at + 60),
+    ).await);
+    assert!(matches!(err, VaultError::StillLocked { unlock_at: at } if at == unlock_at + 3600), "{:?}", err);
+}
+
+#[test]
+fn init_refuses_an_existing_vault() {
+    let vault = Fixture::new();
+    let err = vault_error(init_vault(vault.vault_dir(), "other".to_string()));
+    assert!(matches!(err, VaultError::VaultExists), "{:?}", err);
+    verify_password(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+}
+
+#[test]
+fn duplicate_names_are_rejected() {
+    let vault = Fixture::new();
+    vault.add("a.txt", b"first", now() - 60);
+
+    let err = vault_error(add_file(vault.vault_dir(), vault.plaintext("a.txt", b"second"), PASSWORD.to_string(), now() - 60));
+    assert!(matches!(&err, VaultError::FileExists { name } if name == "a.txt"), "{:?}", err);
+    assert_eq!(vault.entry_names(), ["a.txt"]);
+}
+
+#[test]
+fn invalid_names_are_rejected() {
+    let vault = Fixture::new();
+    let source = vault.plaintext("a.txt", b"data");
+    for name in ["../escape.txt", "dir/file", "", "NUL"] {
+        let err = vault_error(add_file_with_name(vault.vault_dir(), source.clone(), PASSWORD.to_string(), now(), Some(name.to_string())));
+        assert!(matches!(err, VaultError::InvalidName { .. }), "{:?}: {:?}", name, err);
+    }
+    assert!(vault.entry_names().is_empty());
+}
+
+#[tokio::test]
+async fn renames_refuse_taken_and_invalid_names() {
+    let vault = Fixture::new();
+    vault.add("a.txt", b"first", now() - 60);
+    vault.add("b.txt", b"second", now() - 60);
+    let rename = |to: &str| rename_entry(vault.vault_dir(), PASSWORD.to_string(), "a.txt".to_string(), to.to_string());
+
+    let err = vault_error(rename("b.txt"));
+    assert!(matches!(&err, VaultError::FileExists { name } if name == "b.txt"), "{:?}", err);
+    let err = vault_error(rename("../escape.txt"));
+    assert!(matches!(err, VaultError::InvalidName { .. }), "{:?}", err);
+    assert_eq!(vault.entry_names(), ["a.txt", "b.txt"]);
+
+    rename("c.txt").unwrap();
+    unlock_file_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), "c.txt".to_string(), &clock_at(now())).await.unwrap();
+    assert_eq!(fs::read(vault.out.join("c.txt")).unwrap(), b"first");
+}
+
+#[tokio::test]
+async fn locked_file_is_refused() {
+    let vault = Fixture::new();
+    let unlock_at = now() + 3600;
+    vault.add("later.txt", b"not yet", unlock_at);
+
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "later.txt".to_string(),
+        &clock_at(now()),
+    ).await);
+    assert!(matches!(err, VaultError::StillLocked { unlock_at: at } if at == unlock_at), "{:?}", err);
+    assert!(!vault.out.join("later.txt").exists());
+
+    // The same entry opens once the clock reaches its unlock date.
+    unlock_file_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), "later.txt".to_string(), &clock_at(unlock_at)).await.unwrap();
+    assert_eq!(fs::read(vault.out.join("later.txt")).unwrap(), b"not yet");
+}
+
+#[tokio::test]
+async fn unknown_entry_is_not_found() {
+    let vault = Fixture::new();
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "missing.txt".to_string(),
+        &clock_at(now()),
+    ).await);
+    assert!(matches!(&err, VaultError::FileNotFound { name } if name == "missing.txt"), "{:?}", err);
+}
+
+#[tokio::test]
+async fn unverifiable_time_is_refused() {
+    let vault = Fixture::new();
+    vault.add("a.txt", b"secret", now() - 60);
+
+    let no_sources = TimeVerifier::new(vec![], TimePolicy::default());
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "a.txt".to_string(),
+        &no_sources,
+    ).await);
+    assert!(matches!(err, VaultError::TimeUnavailable { .. }), "{:?}", err);
+    assert!(!vault.out.join("a.txt").exists());
+}
+
+#[tokio::test]
+async fn time_regression_is_refused() {
+    let vault = Fixture::new();
+    let t = now();
+    vault.add("a.txt", b"secret", t - 3600);
+
+    // Unlocking records the verified time as the vault's watermark.
+    unlock_file_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), "a.txt".to_string(), &clock_at(t)).await.unwrap();
+    fs::remove_file(vault.out.join("a.txt")).unwrap();
+
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "a.txt".to_string(),
+        &clock_at(t - 60),
+    ).await);
+    assert!(matches!(err, VaultError::TimeRegression), "{:?}", err);
+    assert!(!vault.out.join("a.txt").exists());
+
+    let err = vault_error(unlock_vault_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), &clock_at(t - 60)).await);
+    assert!(matches!(err, VaultError::TimeRegression), "{:?}", err);
+}
+
+/// A verifier with no sources, for operations that must not need the time.
+fn no_clock() -> TimeVerifier {
+    TimeVerifier::new(vec![], TimePolicy::default())
+}
+
+#[tokio::test]
+async fn deletions_are_logged() {
+    let vault = Fixture::new();
+    let unlock_at = now() + 3600;
+    vault.add("later.txt", b"not yet", unlock_at);
+    vault.add("keep.txt", b"stays", unlock_at);
+
+    // The default policy deletes locked entries without asking for the time.
+    vault.delete("later.txt", &no_clock()).await.unwrap();
+    assert_eq!(vault.entry_names(), ["keep.txt"]);
+    assert_eq!(fs::read_dir(vault.vault.join("blobs")).unwrap().count(), 1);
+
+    let log = deletion_log(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].filename, "later.txt");
+    assert_eq!(log[0].file_unlock_date, unlock_at);
+    assert_eq!(log[0].content_sha256.len(), 64);
+    assert!(log[0].deleted_at.abs_diff(now()) < 60);
+
+    let err = vault_error(vault.delete("later.txt", &no_clock()).await);
+    assert!(matches!(err, VaultError::FileNotFound { .. }), "{:?}", err);
+    let err = vault_error(deletion_log(vault.vault_dir(), "wrong".to_string()));
+    assert!(matches!(err, VaultError::WrongPassword), "{:?}", err);
+}
+
+#[tokio::test]
+async fn unlocked_only_policy_refuses_deleting_locked_entries() {
+    let vault = Fixture::new();
+    let t = now();
+    vault.add("later.txt", b"not yet", t + 3600);
+    vault.add("due.txt", b"ready", t - 60);
+    vault.set_policy(DeletePolicy::UnlockedOnly, &no_clock()).await.unwrap();
+
+    let err = vault_error(vault.delete("later.txt", &clock_at(t)).await);
+    assert!(matches!(err, VaultError::DeleteNotAllowed { unlock_at } if unlock_at == t + 3600), "{:?}", err);
+    let err = vault_error(vault.delete("later.txt", &no_clock()).await);
+    assert!(matches!(err, VaultError::TimeUnavailable { .. }), "{:?}", err);
+    assert_eq!(vault.entry_names(), ["due.txt", "later.txt"]);
+    assert!(deletion_log(vault.vault_dir(), PASSWORD.to_string()).unwrap().is_empty());
+
+    // Refused attempts still record the verified time, so an entry already
+    // due by it is deleted without asking again.
+    vault.delete("due.txt", &no_clock()).await.unwrap();
+    vault.delete("later.txt", &clock_at(t + 3600)).await.unwrap();
+    let log = deletion_log(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+    assert_eq!(log.iter().map(|record| record.filename.as_str()).collect::<Vec<_>>(), ["due.txt", "later.txt"]);
+}
+
+#[tokio::test]
+async fn unlocked_only_policy_is_only_relaxed_once_every_entry_is_due() {
+    let vault = Fixture::new();
+    let t = now();
+    vault.add("later.txt", b"not yet", t + 3600);
+    vault.set_policy(DeletePolicy::UnlockedOnly, &no_clock()).await.unwrap();
+
+    let err = vault_error(vault.set_policy(DeletePolicy::Any, &clock_at(t)).await);
+    assert!(matches!(err, VaultError::PolicyLocked { unlock_at } if unlock_at == t + 3600), "{:?}", err);
+    let err = vault_error(vault.set_policy(DeletePolicy::Any, &no_clock()).await);
+    assert!(matches!(err, VaultError::TimeUnavailable { .. }), "{:?}", err);
+    let err = vault_error(vault.delete("later.txt", &clock_at(t)).await);
+    assert!(matches!(err, VaultError::DeleteNotAllowed { .. }), "{:?}", err);
+
+    // Keeping the stricter policy never needs the time.
+    vault.set_policy(DeletePolicy::UnlockedOnly, &no_clock()).await.unwrap();
+
+    vault.set_policy(DeletePolicy::Any, &clock_at(t + 3600)).await.unwrap();
+    vault.add("another.txt", b"later still", t + 7200);
+    vault.delete("another.txt", &no_clock()).await.unwrap();
+}
+
+#[tokio::test]
+async fn tampered_header_is_detected() {
+    let vault = Fixture::new();
+    let t = now();
+    vault.add("a.txt", b"secret", t - 3600);
+    unlock_vault_with_verifier(vault.vault_dir(), vault.out_dir(), PASSWORD.to_string(), &clock_at(t)).await.unwrap();
+
+    // Winding the recorded time back would reopen the regression window.
+    let header_path = vault.vault.join("vault_metadata.json");
+    let mut header: serde_json::Value = serde_json::from_slice(&fs::read(&header_path).unwrap()).unwrap();
+    header["last_verified_time"] = serde_json::json!(0);
+    header["authenticated_time"] = serde_json::json!(0);
+    fs::write(&header_path, serde_json::to_vec(&header).unwrap()).unwrap();
+
+    let err = vault_error(verify_password(vault.vault_dir(), PASSWORD.to_string()));
+    assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+}
+
+#[test]
+fn tampered_argon_params_fail_authentication() {
+    let vault = Fixture::new();
+    let header_path = vault.vault.join("vault_metadata.json");
+
+    // A cheaper derivation gives a different key, which cannot be told apart
+    // from a wrong password.
+    let mut header: serde_json::Value = serde_json::from_slice(&fs::read(&header_path).unwrap()).unwrap();
+    header["argon_iters"] = serde_json::json!(1);
+    fs::write(&header_path, serde_json::to_vec(&header).unwrap()).unwrap();
+    let err = vault_error(verify_password(vault.vault_dir(), PASSWORD.to_string()));
+    assert!(matches!(err, VaultError::WrongPassword), "{:?}", err);
+
+    // Parameters TimeVault would never choose are refused before deriving.
+    header["argon_iters"] = serde_json::json!(u32::MAX);
+    fs::write(&header_path, serde_json::to_vec(&header).unwrap()).unwrap();
+    let err = vault_error(verify_password(vault.vault_dir(), PASSWORD.to_string()));
+    assert!(matches!(err, VaultError::CorruptMetadata { .. }), "{:?}", err);
+}
+
+#[tokio::test]
+async fn tampered_entry_metadata_is_reported() {
+    let vault = Fixture::new();
+    vault.add("a.txt", b"secret", now() - 60);
+
+    let meta_path = vault.only_file_in("files_meta");
+    let mut meta: serde_json::Value = serde_json::from_slice(&fs::read(&meta_path).unwrap()).unwrap();
+    let payload = meta["encrypted_payload_b64"].as_str().unwrap();
+    // Swap one base64 character for another so the record still decodes but
+    // no longer authenticates.
+    let mut chars: Vec<char> = payload.chars().collect();
+    chars[10] = if chars[10] == 'A' { 'B' } else { 'A' };
+    meta["encrypted_payload_b64"] = serde_json::json!(chars.into_iter().collect::<String>());
+    fs::write(&meta_path, serde_json::to_vec(&meta).unwrap()).unwrap();
+
+    let status = get_status_with_password(vault.vault_dir(), PASSWORD.to_string()).unwrap();
+    assert!(status.iter().all(|entry| entry["filename"].is_null()));
+    assert!(status.iter().any(|entry| entry["_tampering_warnings"].is_array()));
+
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "a.txt".to_string(),
+        &clock_at(now()),
+    ).await);
+    assert!(matches!(err, VaultError::FileNotFound { .. }), "{:?}", err);
+    assert!(!vault.out.join("a.txt").exists());
+}
+
+#[tokio::test]
+async fn tampered_blob_is_detected() {
+    let vault = Fixture::new();
+    vault.add("big.bin", &large_content(), now() - 60);
+
+    let blob_path = vault.only_file_in("blobs");
+    let mut blob = fs::read(&blob_path).unwrap();
+    let middle = blob.len() / 2;
+    blob[middle] ^= 0x01;
+    fs::write(&blob_path, blob).unwrap();
+
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "big.bin".to_string(),
+        &clock_at(now()),
+    ).await);
+    assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+    // Neither the output nor the partial file it was decrypted into is left.
+    assert_eq!(fs::read_dir(&vault.out).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn truncated_blob_is_detected() {
+    let vault = Fixture::new();
+    vault.add("big.bin", &large_content(), now() - 60);
+
+    let blob_path = vault.only_file_in("blobs");
+    let blob = fs::read(&blob_path).unwrap();
+    // Dropping whole chunks must not yield a shorter but valid plaintext.
+    let sealed_chunk = DEFAULT_CHUNK_SIZE as usize + 16;
+    fs::write(&blob_path, &blob[..2 * sealed_chunk]).unwrap();
+
+    let err = vault_error(unlock_file_with_verifier(
+        vault.vault_dir(),
+        vault.out_dir(),
+        PASSWORD.to_string(),
+        "big.bin".to_string(),
+        &clock_at(now()),
+    ).await);
+    assert!(matches!(err, VaultError::Tampered { .. }), "{:?}", err);
+    assert!(!vault.out.join("big.bin").exists());
+}
+
+#[test]
+fn opening_a_folder_without_a_vault_leaves_it_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = vault_error(verify_password(s(dir.path()), PASSWORD.to_string()));
+    assert!(matches!(err, VaultError::VaultNotFound), "{:?}", err);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
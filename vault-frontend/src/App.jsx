@@ -7,7 +7,7 @@ import PasswordModal from "./components/PasswordModal";
 import Dashboard from "./components/Dashboard";
 import "./styles/datetimepicker.css";
 import { useTheme } from "./context/ThemeContext";
-import { tauriOpen, tauriInvoke } from "./tauri-wrapper";
+import { tauriOpen, tauriInvoke, tauriListen } from "./tauri-wrapper";
 
 export default function App() {
   const { dark } = useTheme();
@@ -36,18 +36,14 @@ export default function App() {
     existingFilename: "",
     newFilename: "",
     fileToAdd: "",
-    unlockDate: null,
-    password: ""
+    unlockDate: null
   });
-  const [cachedVaultPassword, setCachedVaultPassword] = useState("");
   const [showVaultPasswordModal, setShowVaultPasswordModal] = useState(false);
   const [pendingVaultPath, setPendingVaultPath] = useState("");
   const [isVaultPasswordProcessing, setIsVaultPasswordProcessing] = useState(false);
   const [isDraggingOver, setIsDraggingOver] = useState(false);
   const dragCounterRef = useRef(0);
   const dragOverlayTimeoutRef = useRef(null);
-  const [showUnlockPasswordModal, setShowUnlockPasswordModal] = useState(false);
-  const [fileToUnlock, setFileToUnlock] = useState(null);
 
   useEffect(() => {
     if (pickedFile && screen === "dashboard" && !showAddFile) {
@@ -185,27 +181,13 @@ export default function App() {
     };
   }, [screen]);
 
-  async function refreshVaultStatus(path, silent = false, password = null) {
+  async function refreshVaultStatus(path, silent = false) {
     try {
       if (!silent) {
         appendLog("Verifying saved files...");
       }
       
-      const passwordToUse = password || cachedVaultPassword;
-      
-      if (!passwordToUse) {
-        console.log("No password available for status check");
-        setFiles([]);
-        if (!silent) {
-          appendLog("No password available - skipping file verification");
-        }
-        return;
-      }
-      
-      const fileList = await tauriInvoke("status_with_password", { 
-        vaultPath: path,
-        password: passwordToUse 
-      });
+      const fileList = await tauriInvoke("session_status");
       
       if (Array.isArray(fileList)) {
         const tamperingWarningEntry = fileList.find(item => item._tampering_warnings);
@@ -234,6 +216,9 @@ export default function App() {
       }
     } catch (e) {
       console.error("refreshVaultStatus", e);
+      if (e?.kind === "not_open" || e?.kind === "session_expired") {
+        setFiles([]);
+      }
       appendLog("Error refreshing status: " + (e?.message || e));
     }
   }
@@ -338,15 +323,19 @@ export default function App() {
         vaultDir: vaultPath,
         password: vaultPassword,
       });
+      await tauriInvoke("open_vault_tauri", {
+        vaultDir: vaultPath,
+        password: vaultPassword,
+      });
+      setVaultPassword("");
 
       appendLog("Vault initialized at " + vaultPath);
       setVaultPath(vaultPath);
-      setCachedVaultPassword(vaultPassword);
       setShowCreate(false);
       setScreen("dashboard");
       setLog(""); 
 
-      await refreshVaultStatus(vaultPath, false, vaultPassword);
+      await refreshVaultStatus(vaultPath, false);
       await refreshVaultInfo(vaultPath);
     } catch (e) {
       console.error("initializeVault", e);
@@ -368,63 +357,36 @@ export default function App() {
     }
   };
 
-  const verifyPasswordForAddFile = async (password, statusCallback) => {
-    if (!password) return;
-
-    try {
-      appendLog("Verifying vault password...");
-      if (statusCallback) statusCallback("Verifying vault password...");
-      await tauriInvoke("verify_vault_password", {
-        vaultDir: vaultPath,
-        password,
-      });
-      appendLog("Password verified successfully");
-      if (statusCallback) statusCallback("Password verified successfully");
-      return true;
-    } catch (e) {
-      console.error("verifyPasswordForAddFile", e);
-      const errorMsg = e?.message || e;
-      appendLog("Error: " + errorMsg);
-      if (statusCallback) statusCallback("Error: " + errorMsg);
-      throw e; 
-    }
-  };
-
-  const addFileToVault = async (password, fileToAdd, unlockDate) => {
+  const addFileToVault = async (fileToAdd, unlockDate) => {
     if (!vaultPath) return;
     if (!fileToAdd) return;
     if (!unlockDate) return;
-    if (!password) return;
 
     const unlockUnix = Math.floor(unlockDate.getTime() / 1000);
 
     try {
       appendLog("Encrypting and adding file...");
-      await tauriInvoke("add_file_tauri", {
-        vaultDir: vaultPath,
+      await tauriInvoke("session_add_file", {
         filePath: fileToAdd,
-        password,
         fileUnlockDate: unlockUnix,
       });
 
       appendLog(`File added: ${fileToAdd.split(/[\\/]/).pop()}`);
-      setCachedVaultPassword(password);
-      await refreshVaultStatus(vaultPath, true, password);
+      await refreshVaultStatus(vaultPath, true);
     } catch (e) {
       console.error("addFileToVault", e);
       const errorMsg = e?.message || e;
       
-      if (errorMsg.startsWith("FILE_EXISTS:")) {
-        const existingFilename = errorMsg.split(":")[1];
+      if (e?.kind === "file_exists") {
+        const existingFilename = e.name;
         const newFilename = serializeFilename(existingFilename, 1);
         
-        appendLog(`FILE_EXISTS error detected for: ${existingFilename}`);
+        appendLog(`A file named ${existingFilename} already exists in the vault`);
         setFileExistsData({
           existingFilename,
           newFilename,
           fileToAdd,
-          unlockDate,
-          password
+          unlockDate
         });
         setShowFileExistsDialog(true);
         return; 
@@ -435,7 +397,7 @@ export default function App() {
   };
 
   const handleFileExistsRename = async () => {
-    const { fileToAdd, unlockDate, password } = fileExistsData;
+    const { fileToAdd, unlockDate } = fileExistsData;
     const unlockUnix = Math.floor(unlockDate.getTime() / 1000);
     
     setShowFileExistsDialog(false);
@@ -449,10 +411,8 @@ export default function App() {
         const serializedFilename = serializeFilename(originalFilename, attemptNumber);
         appendLog(`Trying with serialized name: ${serializedFilename}`);
         
-        await tauriInvoke("add_file_with_custom_name", {
-          vaultDir: vaultPath,
+        await tauriInvoke("session_add_file", {
           filePath: fileToAdd,
-          password,
           fileUnlockDate: unlockUnix,
           customFilename: serializedFilename,
         });
@@ -461,8 +421,7 @@ export default function App() {
         await refreshVaultStatus(vaultPath, true);
         success = true;
       } catch (retryError) {
-        const retryMsg = retryError?.message || retryError;
-        if (retryMsg.startsWith("FILE_EXISTS:")) {
+        if (retryError?.kind === "file_exists") {
           attemptNumber++;
         } else {
           throw retryError;
@@ -480,28 +439,17 @@ export default function App() {
     appendLog("File add cancelled by user");
   };
 
-  const unlockSingle = async (file, password, statusCallback) => {
-    if (!vaultPath || !password) return;
+  const unlockSingle = async (file, statusCallback) => {
+    if (!vaultPath) return;
 
     try {
-      appendLog("Verifying vault password...");
-      if (statusCallback) statusCallback("Verifying vault password...");
-      await tauriInvoke("verify_vault_password", {
-        vaultDir: vaultPath,
-        password,
-      });
-      appendLog("Password verified successfully");
-      if (statusCallback) statusCallback("Password verified successfully");
-
       const unlockedDir = vaultPath + (vaultPath.includes('/') ? '/' : '\\') + 'Unlocked Files';
       
       if (statusCallback) statusCallback(`Decrypting and Unlocking file...`);
       const targetName = file.name || file.filename;
       appendLog(`Decrypting and Unlocking file: ${targetName}...`);
-      const result = await tauriInvoke("unlock_file_tauri", {
-        vaultDir: vaultPath,
+      const result = await tauriInvoke("session_unlock_file", {
         outDir: unlockedDir,
-        password,
         filename: targetName,
       });
       try {
@@ -518,35 +466,23 @@ export default function App() {
     }
   };
 
-  const unlockAll = async (password, statusCallback) => {
-    if (!vaultPath || !password) return;
+  const unlockAll = async (statusCallback) => {
+    if (!vaultPath) return;
 
     try {
-      appendLog("Verifying vault password...");
-      if (statusCallback) statusCallback("Verifying vault password");
-      await tauriInvoke("verify_vault_password", {
-        vaultDir: vaultPath,
-        password,
-      });
-      appendLog("Password verified successfully");
-      if (statusCallback) statusCallback("Password verified successfully");
-
       const unlockedDir = vaultPath + (vaultPath.includes('/') ? '/' : '\\') + 'Unlocked Files';
 
       appendLog("Decrypting and unlocking all eligible files...");
       appendLog(`Output directory: ${unlockedDir}`);
       if (statusCallback) statusCallback("Decrypting and unlocking all eligible files...");
       
-      const result = await tauriInvoke("unlock_vault_tauri", {
-        vaultDir: vaultPath,
+      const result = await tauriInvoke("session_unlock_vault", {
         outDir: unlockedDir,
-        password,
       });
       appendLog(result);
       appendLog("Vault unlock complete!");
 
-      setCachedVaultPassword(password);
-      await refreshVaultStatus(vaultPath, false, password);
+      await refreshVaultStatus(vaultPath, false);
       await refreshVaultInfo(vaultPath);
     } catch (e) {
       console.error("unlockAll", e);
@@ -560,19 +496,18 @@ export default function App() {
   const handleVaultPasswordSubmit = async (password) => {
     setIsVaultPasswordProcessing(true);
     try {
-      await tauriInvoke("verify_vault_password", {
+      await tauriInvoke("open_vault_tauri", {
         vaultDir: pendingVaultPath,
         password,
       });
       
       setVaultPath(pendingVaultPath);
-      setCachedVaultPassword(password);
       setShowVaultPasswordModal(false);
       setPendingVaultPath("");
       setScreen("dashboard");
       setLog("");
       
-      await refreshVaultStatus(pendingVaultPath, false, password);
+      await refreshVaultStatus(pendingVaultPath, false);
       await refreshVaultInfo(pendingVaultPath);
     } catch (e) {
       console.error("handleVaultPasswordSubmit", e);
@@ -589,7 +524,7 @@ export default function App() {
   };
 
   const exitVault = () => {
-    setCachedVaultPassword("");
+    tauriInvoke("lock_vault").catch(() => {});
     setVaultPath("");
     setFiles([]);
     setLog("");
@@ -598,6 +533,17 @@ export default function App() {
     setScreen("splash");
   };
 
+  useEffect(() => {
+    let unlisten;
+    tauriListen("vault-locked", () => {
+      exitVault();
+      appendLog("Vault locked after inactivity");
+    }).then((fn) => {
+      unlisten = fn;
+    });
+    return () => unlisten?.();
+  }, []);
+
   const formatDate = (v) => {
     if (v === null || v === undefined || v === "—") return "—";
     if (typeof v === "number") return new Date(v * 1000).toLocaleString();
@@ -676,7 +622,6 @@ export default function App() {
             unlockSingle={unlockSingle}
             onExit={exitVault}
             pickFileForAdd={pickFileForAdd}
-            onPasswordVerified={setCachedVaultPassword}
           />
         )}
       </main>
@@ -740,13 +685,12 @@ export default function App() {
         setPickedFile={setPickedFile}
         fileUnlockDate={fileUnlockDate}
         setFileUnlockDate={setFileUnlockDate}
-        verifyPassword={verifyPasswordForAddFile}
-        onPasswordVerified={(password) => {
+        onAdd={() => {
           const fileToAdd = pickedFile;
           const unlockDate = fileUnlockDate;
           setPickedFile("");
           setFileUnlockDate(null);
-          addFileToVault(password, fileToAdd, unlockDate);
+          addFileToVault(fileToAdd, unlockDate);
         }}
         pickFileForAdd={pickFileForAdd}
       />
@@ -1,4 +1,4 @@
-import React, { useState } from "react";
+import React from "react";
 import { createPortal } from "react-dom";
 
 export default function AddFileModal({
@@ -7,15 +7,9 @@ export default function AddFileModal({
   setPickedFile,
   fileUnlockDate,
   setFileUnlockDate,
-  verifyPassword,
-  onPasswordVerified,
+  onAdd,
   pickFileForAdd,
 }) {
-  const [password, setPassword] = useState("");
-  const [showPasswordField, setShowPasswordField] = useState(false);
-  const [isProcessing, setIsProcessing] = useState(false);
-  const [statusMessage, setStatusMessage] = useState("");
-
   const handleAdd = () => {
     if (!pickedFile) {
       alert("Please select a file");
@@ -25,63 +19,16 @@ export default function AddFileModal({
       alert("Please set unlock date");
       return;
     }
-    setShowPasswordField(true);
-  };
-
-  const handleBack = () => {
-
-    setPassword("");
-    setShowPasswordField(false);
-    setStatusMessage("");
-    setIsProcessing(false);
+    setShowAddFile(false);
+    onAdd();
   };
 
   const handleCancel = () => {
-
     setShowAddFile(false);
-      setPassword("");
-      setShowPasswordField(false);
-      setStatusMessage("");
-      setIsProcessing(false);
     setPickedFile("");
     setFileUnlockDate(null);
   };
 
-  const handleConfirm = async () => {
-    if (!password) {
-      alert("Please enter vault password");
-      return;
-    }
-    setIsProcessing(true);
-    setStatusMessage("Verifying vault password...");
-    try {
-
-      await verifyPassword(password, (status) => {
-        setStatusMessage(status);
-      });
-      setStatusMessage("Password verified successfully");
-
-      const verifiedPassword = password;
-      
-      setTimeout(() => {
-        setShowAddFile(false);
-        setPassword("");
-        setShowPasswordField(false);
-        setStatusMessage("");
-        setIsProcessing(false);
-
-        if (onPasswordVerified) {
-          onPasswordVerified(verifiedPassword);
-        }
-      }, 500);
-    } catch (error) {
-      console.error('Password verification failed:', error);
-      const errorMsg = error?.message || error || "An error occurred";
-      setStatusMessage(errorMsg);
-      setIsProcessing(false);
-    }
-  };
-
   const modalContent = (
     <>
       <div 
@@ -118,7 +65,6 @@ export default function AddFileModal({
             onMouseEnter={(e) => e.target.style.color = '#dc2626'}
             onMouseLeave={(e) => e.target.style.color = '#ef4444'}
             aria-label="Close"
-            disabled={isProcessing}
           >
             ×
           </button>
@@ -141,13 +87,11 @@ export default function AddFileModal({
                   placeholder="Select a file to add"
                   className="flex-1 rounded border border-gray-300 dark:border-gray-600 bg-gray-50 dark:bg-gray-800 dark:text-gray-100 px-3 text-sm"
                   style={{ height: '25px' }}
-                  disabled={showPasswordField}
                 />
                 <button
                   onClick={pickFileForAdd}
                   className="px-4 bg-indigo-600 text-white rounded hover:bg-indigo-700 text-sm disabled:opacity-60"
                   style={{ height: '25px' }}
-                  disabled={showPasswordField}
                 >
                   Browse
                 </button>
@@ -168,80 +112,17 @@ export default function AddFileModal({
                 onChange={(e) => setFileUnlockDate(new Date(e.target.value))}
                 className="w-full rounded border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 dark:text-gray-100 px-3 text-sm"
                 style={{ height: '25px' }}
-                disabled={showPasswordField}
               />
             </div>
-
-            {showPasswordField && (
-              <div>
-                <label className="block text-sm font-medium mb-2 dark:text-gray-200">
-                  Vault Password
-                </label>
-                <input
-                  type="password"
-                  value={password}
-                  onChange={(e) => setPassword(e.target.value)}
-                  onKeyPress={(e) => e.key === 'Enter' && handleConfirm()}
-                  placeholder="Enter vault password"
-                  className="w-full rounded border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 dark:text-gray-100 px-3 text-sm"
-                  style={{ height: '25px', lineHeight: '25px', paddingTop: 0, paddingBottom: 0 }}
-                  autoComplete="current-password"
-                  disabled={isProcessing}
-                  autoFocus
-                />
-              </div>
-            )}
-
-            {showPasswordField && statusMessage && (
-              <div className="mt-3">
-                <div className="flex items-center gap-2 text-xs font-mono text-gray-700 dark:text-gray-300">
-                  {statusMessage === "Success!" && (
-                    <span className="text-green-600 dark:text-green-400">✓</span>
-                  )}
-                  {statusMessage.startsWith("Error") && (
-                    <span className="text-red-600 dark:text-red-400">✗</span>
-                  )}
-                  <span className={
-                    statusMessage === "Success!" 
-                      ? "text-green-600 dark:text-green-400" 
-                      : statusMessage.startsWith("Error")
-                      ? "text-red-600 dark:text-red-400"
-                      : ""
-                  }>{statusMessage}</span>
-                  {isProcessing && statusMessage !== "Success!" && !statusMessage.startsWith("Error") && (
-                    <span className="inline-block animate-pulse">⋯</span>
-                  )}
-                </div>
-              </div>
-            )}
           </div>
 
           <div className="flex justify-end gap-3 mt-6 max-w-[400px] mx-auto">
-            {showPasswordField && (
-              <button
-                onClick={handleBack}
-                className="px-5 py-2 border border-gray-300 dark:border-gray-600 rounded hover:bg-gray-50 dark:hover:bg-gray-800 text-sm dark:text-gray-200"
-                disabled={isProcessing}
-              >
-                Back
-              </button>
-            )}
-            {!showPasswordField ? (
-              <button
-                onClick={handleAdd}
-                className="px-5 py-2 bg-indigo-600 text-white rounded hover:bg-indigo-700 text-sm"
-              >
-                Add File
-              </button>
-            ) : (
-              <button
-                onClick={handleConfirm}
-                className="px-5 py-2 bg-green-600 text-white rounded hover:bg-green-700 text-sm disabled:opacity-60"
-                disabled={isProcessing}
-              >
-                {isProcessing ? "Adding..." : "Confirm"}
-              </button>
-            )}
+            <button
+              onClick={handleAdd}
+              className="px-5 py-2 bg-indigo-600 text-white rounded hover:bg-indigo-700 text-sm"
+            >
+              Add File
+            </button>
           </div>
         </div>
       </div>
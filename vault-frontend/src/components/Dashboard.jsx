@@ -1,5 +1,4 @@
 import React, { useState, useEffect, useRef } from "react";
-import { tauriInvoke } from "../tauri-wrapper";
 
 export default function Dashboard({ 
   vaultPath, 
@@ -12,12 +11,10 @@ export default function Dashboard({
   unlockAll, 
   unlockSingle,
   onExit,
-  pickFileForAdd,
-  onPasswordVerified
+  pickFileForAdd
 }) {
-  const [password, setPassword] = useState("");
-  const [showPasswordField, setShowPasswordField] = useState(false);
-  const [passwordAction, setPasswordAction] = useState(null); 
+  const [showConfirm, setShowConfirm] = useState(false);
+  const [pendingAction, setPendingAction] = useState(null);
   const [selectedFile, setSelectedFile] = useState(null);
   const [isProcessing, setIsProcessing] = useState(false);
   const [wiggling, setWiggling] = useState(null);
@@ -99,72 +96,47 @@ export default function Dashboard({
 
   const handleUnlockFile = (file) => {
     setSelectedFile(file);
-    setPasswordAction('unlock-file');
-    setShowPasswordField(true);
+    setPendingAction('unlock-file');
+    setShowConfirm(true);
     setStatusMessage('');
     setIsProcessing(false);
   };
 
   const handleUnlockVault = () => {
-    setPasswordAction('unlock-vault');
-    setShowPasswordField(true);
+    setPendingAction('unlock-vault');
+    setShowConfirm(true);
     setStatusMessage('');
     setIsProcessing(false);
   };
 
-  const handleCancelPassword = () => {
-    setPassword("");
-    setShowPasswordField(false);
+  const closeConfirm = () => {
+    setShowConfirm(false);
     setSelectedFile(null);
-    setPasswordAction(null);
+    setPendingAction(null);
     setStatusMessage("");
+    setIsProcessing(false);
   };
 
-  const handleConfirmPassword = async () => {
-    if (!password) {
-      alert("Please enter vault password");
-      return;
-    }
+  const handleConfirm = async () => {
     setIsProcessing(true);
-    setStatusMessage("Verifying vault password...");
-    
-    try {
-      if (passwordAction === 'unlock-file') {
-        // Verify password only, close prompt, then perform unlock with logs in activity log
-        await tauriInvoke("verify_vault_password", { vaultDir: vaultPath, password });
-
-        if (onPasswordVerified) onPasswordVerified(password);
 
-        // Close inline prompt immediately on success
-        setPassword("");
-        setShowPasswordField(false);
-        setSelectedFile(null);
-        setPasswordAction(null);
-        setStatusMessage("");
-        setIsProcessing(false);
-
-        unlockSingle(selectedFile, password);
+    try {
+      if (pendingAction === 'unlock-file') {
+        // Close the prompt right away; progress goes to the activity log
+        const file = selectedFile;
+        closeConfirm();
+        await unlockSingle(file);
         return;
-      } else if (passwordAction === 'unlock-vault') {
-        await unlockAll(password, (status) => {
+      } else if (pendingAction === 'unlock-vault') {
+        await unlockAll((status) => {
           setStatusMessage(status);
         });
       }
-      
-      if (onPasswordVerified) {
-        onPasswordVerified(password);
-      }
-      
+
       setStatusMessage("Success!");
-      setTimeout(() => {
-        setPassword("");
-        setShowPasswordField(false);
-        setSelectedFile(null);
-        setPasswordAction(null);
-        setStatusMessage("");
-      }, 800);
+      setTimeout(closeConfirm, 800);
     } catch (error) {
-      console.error('Password action failed:', error);
+      console.error('Unlock failed:', error);
       setStatusMessage("");
       setIsProcessing(false);
     }
@@ -319,7 +291,7 @@ export default function Dashboard({
 
         <div className="flex justify-center mb-6" style={{ marginTop: '40px' }}>
           <div className="flex items-center justify-center gap-64 py-12 px-12 w-full">
-            {!showPasswordField ? (
+            {!showConfirm ? (
               <>
                 <button
                   onClick={async () => {
@@ -351,26 +323,14 @@ export default function Dashboard({
                 <div className="flex items-end gap-3 justify-center">
                   <div>
                     <label className="block text-xs font-medium mb-1 dark:text-gray-200">
-                      {passwordAction === 'unlock-file' 
-                        ? `Enter Vault Password to unlock ${selectedFile?.filename || selectedFile?.name || 'file'}` 
-                        : 'Unlock all eligible files'}
+                      {pendingAction === 'unlock-file' 
+                        ? `Unlock ${selectedFile?.filename || selectedFile?.name || 'file'}?` 
+                        : 'Unlock all eligible files?'}
                     </label>
-                    <input
-                      type="password"
-                      value={password}
-                      onChange={(e) => setPassword(e.target.value)}
-                      onKeyPress={(e) => e.key === 'Enter' && handleConfirmPassword()}
-                      placeholder="Enter vault password"
-                      className="rounded border border-gray-300 dark:border-gray-600 bg-white dark:bg-gray-800 dark:text-gray-100 px-3 text-sm"
-                      style={{ height: '32px', lineHeight: '32px', paddingTop: 0, paddingBottom: 0, width: '300px' }}
-                      autoComplete="current-password"
-                      disabled={isProcessing}
-                      autoFocus
-                    />
                   </div>
                   <div className="flex gap-3" style={{ marginLeft: '4px' }}>
                     <button
-                      onClick={handleCancelPassword}
+                      onClick={closeConfirm}
                       disabled={isProcessing}
                       className="px-6 py-2 border-2 border-gray-300 dark:border-gray-600 rounded-lg hover:bg-gray-50 dark:hover:bg-gray-800 text-sm font-semibold dark:text-gray-200 disabled:opacity-50 transition-all duration-200"
                       style={{ height: '32px', lineHeight: '32px' }}
@@ -378,7 +338,7 @@ export default function Dashboard({
                       Cancel
                     </button>
                     <button
-                      onClick={handleConfirmPassword}
+                      onClick={handleConfirm}
                       disabled={isProcessing}
                       className="px-6 py-2 bg-green-600 text-white rounded-lg hover:bg-green-700 text-sm font-semibold disabled:opacity-50 transition-all duration-200 shadow-md hover:shadow-lg"
                       style={{ height: '32px', lineHeight: '32px' }}
@@ -417,7 +377,7 @@ export default function Dashboard({
 
           </div>
 
-          {log && !showPasswordField && (
+          {log && !showConfirm && (
         <div className="fixed left-4 right-4 bg-white dark:bg-[#1a1a24] shadow-lg rounded-lg overflow-hidden" style={{ zIndex: 5, bottom: '60px', marginLeft: '10px', marginRight: '10px' }}>
           <div className="px-4 py-3">
               <div className="flex items-center justify-between mb-2">
@@ -31,3 +31,8 @@ export const tauriInvoke = async (cmd, payload) => {
     throw err;
   }
 };
+
+export const tauriListen = async (event, handler) => {
+  const { listen } = await import("@tauri-apps/api/event");
+  return listen(event, handler);
+};